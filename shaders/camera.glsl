@@ -0,0 +1,12 @@
+// Camera the frame is rendered from, uploaded once per frame by engine::CameraBuffer.
+// Must match CameraData in src/engine/camera.rs
+
+layout (std140) uniform Camera
+{
+	mat4 proj;
+	mat4 view;
+	mat4 inverse_proj;
+	mat4 inverse_view;
+	vec3 camera_pos;
+	float camera_near;
+};
\ No newline at end of file
@@ -0,0 +1,14 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec3 FragPos;
+in vec2 TexCoords;
+in vec3 Normal;
+
+#include "../camera.glsl"
+
+#include "surface.glsl"
+
+void main() {
+	FragColor = shadeFragment();
+}
\ No newline at end of file
@@ -0,0 +1,25 @@
+#version 330 core
+// Weighted blended order independent transparency, McGuire and Bavoil 2013.
+// Blended with ONE, ONE for color and ZERO, ONE_MINUS_SRC_ALPHA for alpha,
+// so accum.a ends up as the product of (1 - alpha), the revealage.
+layout (location = 0) out vec4 accum;
+layout (location = 1) out float weight_sum;
+
+in vec3 FragPos;
+in vec2 TexCoords;
+in vec3 Normal;
+
+#include "../camera.glsl"
+
+#include "surface.glsl"
+
+void main() {
+	vec4 color = shadeFragment();
+
+	// Favors near surfaces, view distance works with any depth range
+	float z = abs((view * vec4(FragPos, 1.0)).z);
+	float weight = clamp(10.0 / (1e-5 + pow(z / 5.0, 2.0) + pow(z / 200.0, 6.0)), 1e-2, 3e3);
+
+	accum = vec4(color.rgb * color.a * weight, color.a);
+	weight_sum = color.a * weight;
+}
\ No newline at end of file
@@ -0,0 +1,21 @@
+#version 330 core
+// Resolves the weighted blended transparency buffers over the lit image.
+// Blended with ONE_MINUS_SRC_ALPHA, SRC_ALPHA.
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D accum;
+uniform sampler2D weight_sum;
+
+void main() {
+	vec4 color = texture(accum, TexCoords);
+	float revealage = color.a;
+	if (revealage >= 1.0) {
+		discard;
+	}
+
+	float weight = texture(weight_sum, TexCoords).r;
+	vec3 average = color.rgb / max(weight, 1e-5);
+
+	FragColor = vec4(average, revealage);
+}
\ No newline at end of file
@@ -0,0 +1,52 @@
+// Material sampling and lighting shared by the forward pass shaders.
+// Expects FragPos, TexCoords, Normal and shaders/camera.glsl to be declared.
+
+// material parameters, same as shaders/pbr/geometry.fs
+uniform vec3  material_albedo;
+uniform float material_metallic;
+uniform float material_roughness;
+uniform float material_ao;
+uniform float material_alpha;
+
+uniform sampler2D albedo_map;
+uniform sampler2D metallic_roughness_map;
+uniform sampler2D ao_map;
+uniform bool use_albedo_map;
+uniform bool use_metallic_roughness_map;
+uniform bool use_ao_map;
+
+#include "../pbr/lighting.glsl"
+
+// Lit color and alpha of the current fragment.
+vec4 shadeFragment() {
+	vec3 albedo = material_albedo;
+	float metallic = material_metallic;
+	float roughness = material_roughness;
+	float ao = material_ao;
+	float alpha = material_alpha;
+
+	if (use_albedo_map) {
+		vec4 albedo_sample = texture(albedo_map, TexCoords);
+		albedo *= albedo_sample.rgb;
+		alpha *= albedo_sample.a;
+	}
+	if (use_metallic_roughness_map) {
+		vec3 mr = texture(metallic_roughness_map, TexCoords).rgb;
+		metallic *= mr.b;
+		roughness *= mr.g;
+	}
+	if (use_ao_map) {
+		ao *= texture(ao_map, TexCoords).r;
+	}
+
+	vec3 N = normalize(Normal);
+	vec3 V = normalize(camera_pos - FragPos);
+	// Back faces of double sided surfaces are lit from their side
+	if (!gl_FrontFacing) {
+		N = -N;
+	}
+
+	// Linear HDR, blended into the lighting pass output
+	vec3 color = shadeSurface(FragPos, N, V, albedo, metallic, roughness, ao);
+	return vec4(color, alpha);
+}
\ No newline at end of file
@@ -0,0 +1,22 @@
+#version 330 core
+out vec4 FragColor;
+in vec3 LocalPos;
+
+uniform sampler2D equirectangular_map;
+
+const vec2 invAtan = vec2(0.1591, 0.3183);
+
+// Maps a direction to longitude and latitude in the equirectangular image
+vec2 sampleSphericalMap(vec3 v) {
+	vec2 uv = vec2(atan(v.z, v.x), asin(v.y));
+	uv *= invAtan;
+	uv += 0.5;
+	return uv;
+}
+
+void main() {
+	vec2 uv = sampleSphericalMap(normalize(LocalPos));
+	vec3 color = texture(equirectangular_map, uv).rgb;
+
+	FragColor = vec4(color, 1.0);
+}
\ No newline at end of file
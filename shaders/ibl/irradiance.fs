@@ -0,0 +1,33 @@
+#version 330 core
+out vec4 FragColor;
+in vec3 LocalPos;
+
+uniform samplerCube environment_map;
+
+const float PI = 3.14159265359;
+
+// Convolves the hemisphere around the normal with a cosine lobe
+void main() {
+	vec3 N = normalize(LocalPos);
+
+	vec3 up = abs(N.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+	vec3 right = normalize(cross(up, N));
+	up = normalize(cross(N, right));
+
+	vec3 irradiance = vec3(0.0);
+	float sampleDelta = 0.025;
+	float samples = 0.0;
+	for (float phi = 0.0; phi < 2.0 * PI; phi += sampleDelta) {
+		for (float theta = 0.0; theta < 0.5 * PI; theta += sampleDelta) {
+			// Spherical to cartesian in tangent space, then to world space
+			vec3 tangentSample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+			vec3 sampleVec = tangentSample.x * right + tangentSample.y * up + tangentSample.z * N;
+
+			irradiance += texture(environment_map, sampleVec).rgb * cos(theta) * sin(theta);
+			samples++;
+		}
+	}
+	irradiance = PI * irradiance / samples;
+
+	FragColor = vec4(irradiance, 1.0);
+}
\ No newline at end of file
@@ -19,12 +19,47 @@ uniform vec3  material_albedo;
 uniform float material_metallic;
 uniform float material_roughness;
 uniform float material_ao;
+uniform float material_alpha;
+// fragments below this alpha are discarded, 0 keeps everything
+uniform float alpha_cutoff;
+
+// material maps, multiplied with the parameters above
+uniform sampler2D albedo_map;
+uniform sampler2D metallic_roughness_map;
+uniform sampler2D ao_map;
+uniform bool use_albedo_map;
+uniform bool use_metallic_roughness_map;
+uniform bool use_ao_map;
 
 void main() {
     position_out = FragPos;
 	normal_out = normalize(Normal);
 
+	vec3 albedo = material_albedo;
+	float metallic = material_metallic;
+	float roughness = material_roughness;
+	float ao = material_ao;
+
+	float alpha = material_alpha;
+
+	if (use_albedo_map) {
+		vec4 albedo_sample = texture(albedo_map, TexCoords);
+		albedo *= albedo_sample.rgb;
+		alpha *= albedo_sample.a;
+	}
+	if (alpha < alpha_cutoff) {
+		discard;
+	}
+	if (use_metallic_roughness_map) {
+		vec3 mr = texture(metallic_roughness_map, TexCoords).rgb;
+		metallic *= mr.b;
+		roughness *= mr.g;
+	}
+	if (use_ao_map) {
+		ao *= texture(ao_map, TexCoords).r;
+	}
+
 	// Stores material data into texture
-	albedo_out = /*texture(texture1, TexCoords).rgb **/ material_albedo;
-	material_out = /*texture(texture1, TexCoords).rgb **/ vec3(material_metallic, material_roughness, material_ao);
+	albedo_out = albedo;
+	material_out = vec3(metallic, roughness, ao);
 }
\ No newline at end of file
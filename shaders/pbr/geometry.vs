@@ -3,11 +3,7 @@ layout (location = 0) in vec3 aPos;
 layout (location = 1) in vec3 aNormal;
 layout (location = 2) in vec2 aTexCoords;
 
-layout (std140) uniform Matrices
-{
-	mat4 proj;
-	mat4 view;
-};
+#include "../camera.glsl"
 
 uniform mat4 model;
 uniform mat4 normal_mat;
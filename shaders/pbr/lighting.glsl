@@ -0,0 +1,306 @@
+// Lights, shadows and image based lighting for PBR shading.
+// Included by shaders that call shadeSurface.
+
+#define MAX_LIGHTS 64
+#define LIGHT_POINT 0
+#define LIGHT_SPOT 1
+#define LIGHT_DIRECTIONAL 2
+
+struct Light {
+	vec4 position;  // xyz position, w range
+	vec4 direction; // xyz direction, w kind
+	vec4 color;     // rgb color, a intensity
+	vec4 cone;      // x cos inner angle, y cos outer angle
+	vec4 shadow;    // x slot or -1, y bias, z texel size or far plane, w kind of shadow map
+	mat4 light_space;
+};
+
+layout (std140) uniform Lights
+{
+	Light lights[MAX_LIGHTS];
+	int light_count;
+};
+
+#define MAX_SHADOW_MAPS 4
+uniform sampler2D shadow_maps[MAX_SHADOW_MAPS];
+
+#define MAX_POINT_SHADOW_MAPS 4
+uniform samplerCube point_shadow_maps[MAX_POINT_SHADOW_MAPS];
+
+// Cascaded shadow map of one directional light
+#define MAX_CASCADES 4
+uniform sampler2DArray cascade_map;
+uniform mat4 cascade_matrices[MAX_CASCADES];
+uniform float cascade_splits[MAX_CASCADES];
+uniform int cascade_count;
+uniform mat4 cascade_view;
+uniform bool cascade_debug;
+
+// Image based lighting, see src/engine/ibl.rs
+uniform bool use_ibl;
+uniform samplerCube irradiance_map;
+uniform samplerCube prefilter_map;
+uniform sampler2D brdf_lut;
+uniform float prefilter_max_lod;
+uniform float environment_intensity;
+
+// Constants
+const float PI = 3.14159265359;
+const vec3 Fdielectric = vec3(0.04);
+
+// Define functions
+float distributionGGX(vec3 N, vec3 H, float roughness);
+float geometrySchlickGGX(float NdotV, float roughness);
+float geometrySmith(vec3 N, vec3 V, vec3 L, float roughness);
+vec3 fresnelSchlick(float cosTheta, vec3 F0);
+vec3 fresnelSchlickRoughness(float cosTheta, vec3 F0, float roughness);
+vec3 lightRadiance(Light light, vec3 position, out vec3 L);
+float shadowVisibility(Light light, vec3 position, vec3 normal, vec3 L);
+float cascadeVisibility(Light light, vec3 position, vec3 normal, vec3 L);
+float pointVisibility(Light light, vec3 position);
+int cascadeIndex(vec3 position);
+
+// Lit color of a surface point, with V pointing from the surface to the camera.
+// Shared by the deferred lighting pass and the forward pass.
+vec3 shadeSurface(vec3 position, vec3 normal, vec3 V, vec3 albedo, float metallic, float roughness, float ao) {
+    // Reflectance at normal incidence angle
+    vec3 F0 = mix(Fdielectric, albedo, metallic);
+
+    // Reflection vector
+    vec3 R = reflect(-V, normal);
+
+    // Light contribution
+    vec3 Lo = vec3(0.0, 0.0, 0.0);
+    for (int i = 0; i < light_count; i++) {
+        vec3 L;
+        vec3 radiance = lightRadiance(lights[i], position, L);
+        radiance *= shadowVisibility(lights[i], position, normal, L);
+        vec3 H = normalize(V + L);
+
+        // BRDF
+        float NDF = distributionGGX(normal, H, roughness);
+        float G = geometrySmith(normal, V, L, roughness);
+        vec3 F = fresnelSchlick(clamp(dot(H, V), 0.0, 1.0), F0);
+
+        vec3 nominator = NDF * G * F;
+        float denominator = 4 * max(dot(normal, V), 0.0) * max(dot(normal, L), 0.0);
+        vec3 specular = nominator / max(denominator, 0.001);
+
+        vec3 kS = F;
+        vec3 kD = vec3(1.0) - kS;
+        kD *= 1.0 - metallic;
+
+        float NdotL = max(dot(normal, L), 0.0);
+
+        Lo += (kD * albedo / PI + specular) * radiance * NdotL;
+    }
+
+    // Calculate ambient lighting from IBL
+    vec3 F = fresnelSchlickRoughness(max(dot(normal, V), 0.0), F0, roughness);
+    vec3 kD = 1.0 - F;
+    kD *= 1.0 - metallic;
+
+    vec3 ambient;
+    if (use_ibl) {
+        vec3 irradiance = texture(irradiance_map, normal).rgb;
+        vec3 diffuse = irradiance * albedo;
+
+        // Split sum approximation of the specular part
+        vec3 prefiltered = textureLod(prefilter_map, R, roughness * prefilter_max_lod).rgb;
+        vec2 brdf = texture(brdf_lut, vec2(max(dot(normal, V), 0.0), roughness)).rg;
+        vec3 specular = prefiltered * (F * brdf.x + brdf.y);
+
+        ambient = (kD * diffuse + specular) * ao * environment_intensity;
+    } else {
+        ambient = albedo * kD * ao;
+    }
+
+    return ambient + Lo;
+}
+
+// Returns light reaching position and the direction towards the light in L.
+vec3 lightRadiance(Light light, vec3 position, out vec3 L) {
+    int kind = int(light.direction.w);
+    vec3 radiance = light.color.rgb * light.color.a;
+
+    if (kind == LIGHT_DIRECTIONAL) {
+        L = normalize(-light.direction.xyz);
+        return radiance;
+    }
+
+    vec3 to_light = light.position.xyz - position;
+    float distance = length(to_light);
+    L = to_light / distance;
+
+    // Inverse square falloff windowed to reach zero at range
+    float range = light.position.w;
+    float window = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    float attenuation = window * window / (distance * distance + 1.0);
+
+    if (kind == LIGHT_SPOT) {
+        float theta = dot(L, normalize(-light.direction.xyz));
+        float epsilon = max(light.cone.x - light.cone.y, 0.0001);
+        attenuation *= clamp((theta - light.cone.y) / epsilon, 0.0, 1.0);
+    }
+
+    return radiance * attenuation;
+}
+
+// Sampler arrays can only be indexed with constants in GLSL 330
+float sampleShadowMap(int slot, vec2 uv) {
+    if (slot == 0) return texture(shadow_maps[0], uv).r;
+    if (slot == 1) return texture(shadow_maps[1], uv).r;
+    if (slot == 2) return texture(shadow_maps[2], uv).r;
+    return texture(shadow_maps[3], uv).r;
+}
+
+// Fraction of the light that reaches position, filtered with 3x3 PCF.
+float shadowVisibility(Light light, vec3 position, vec3 normal, vec3 L) {
+    if (light.shadow.w > 1.5) {
+        return pointVisibility(light, position);
+    }
+    if (light.shadow.w > 0.5) {
+        return cascadeVisibility(light, position, normal, L);
+    }
+
+    int slot = int(light.shadow.x);
+    if (slot < 0) {
+        return 1.0;
+    }
+
+    vec4 light_pos = light.light_space * vec4(position, 1.0);
+    vec3 coords = light_pos.xyz / light_pos.w * 0.5 + 0.5;
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+
+    // Surfaces at grazing angles need more bias
+    float bias = max(light.shadow.y * (1.0 - dot(normal, L)), light.shadow.y * 0.1);
+    float texel = light.shadow.z;
+
+    float shadow = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            float depth = sampleShadowMap(slot, coords.xy + vec2(x, y) * texel);
+            shadow += coords.z - bias > depth ? 1.0 : 0.0;
+        }
+    }
+
+    return 1.0 - shadow / 9.0;
+}
+
+float samplePointShadowMap(int slot, vec3 direction) {
+    if (slot == 0) return texture(point_shadow_maps[0], direction).r;
+    if (slot == 1) return texture(point_shadow_maps[1], direction).r;
+    if (slot == 2) return texture(point_shadow_maps[2], direction).r;
+    return texture(point_shadow_maps[3], direction).r;
+}
+
+// Cube map PCF, samples spread around the direction to the fragment.
+float pointVisibility(Light light, vec3 position) {
+    const vec3 offsets[20] = vec3[](
+        vec3( 1,  1,  1), vec3( 1, -1,  1), vec3(-1, -1,  1), vec3(-1,  1,  1),
+        vec3( 1,  1, -1), vec3( 1, -1, -1), vec3(-1, -1, -1), vec3(-1,  1, -1),
+        vec3( 1,  1,  0), vec3( 1, -1,  0), vec3(-1, -1,  0), vec3(-1,  1,  0),
+        vec3( 1,  0,  1), vec3(-1,  0,  1), vec3( 1,  0, -1), vec3(-1,  0, -1),
+        vec3( 0,  1,  1), vec3( 0, -1,  1), vec3( 0, -1, -1), vec3( 0,  1, -1)
+    );
+
+    int slot = int(light.shadow.x);
+    float far_plane = light.shadow.z;
+    vec3 to_fragment = position - light.position.xyz;
+    float current = length(to_fragment);
+    if (current > far_plane) {
+        return 1.0;
+    }
+
+    float radius = 0.02 * (1.0 + current / far_plane);
+    float shadow = 0.0;
+    for (int i = 0; i < 20; i++) {
+        float closest = samplePointShadowMap(slot, to_fragment + offsets[i] * radius) * far_plane;
+        shadow += current - light.shadow.y > closest ? 1.0 : 0.0;
+    }
+
+    return 1.0 - shadow / 20.0;
+}
+
+// Cascade covering position, or -1 past the last split.
+int cascadeIndex(vec3 position) {
+    float depth = -(cascade_view * vec4(position, 1.0)).z;
+    for (int i = 0; i < cascade_count; i++) {
+        if (depth < cascade_splits[i]) {
+            return i;
+        }
+    }
+    return -1;
+}
+
+float cascadeVisibility(Light light, vec3 position, vec3 normal, vec3 L) {
+    int cascade = cascadeIndex(position);
+    if (cascade < 0) {
+        return 1.0;
+    }
+
+    vec4 light_pos = cascade_matrices[cascade] * vec4(position, 1.0);
+    vec3 coords = light_pos.xyz / light_pos.w * 0.5 + 0.5;
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+
+    float bias = max(light.shadow.y * (1.0 - dot(normal, L)), light.shadow.y * 0.1);
+    float texel = light.shadow.z;
+
+    float shadow = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec2 uv = coords.xy + vec2(x, y) * texel;
+            float depth = texture(cascade_map, vec3(uv, cascade)).r;
+            shadow += coords.z - bias > depth ? 1.0 : 0.0;
+        }
+    }
+
+    return 1.0 - shadow / 9.0;
+}
+
+// Normal distribution function
+float distributionGGX(vec3 N, vec3 H, float roughness) {
+    float a = roughness*roughness;
+    float a2 = a*a;
+    float NdotH = max(dot(N, H), 0.0);
+    float NdotH2 = NdotH*NdotH;
+
+    float denom = (NdotH2 * (a2 - 1.0) + 1.0);
+    denom = PI * denom * denom;
+
+    return a2 / denom;
+}
+
+// Used by method below
+float geometrySchlickGGX(float NdotV, float roughness) {
+    float r = (roughness + 1.0);
+    float k = (r*r) / 8.0;
+
+    float denom = NdotV * (1.0 - k) + k;
+
+    return NdotV / denom;
+}
+
+// Normal distribution function. Describes self-shadowing of microfacets. When a surface is very rough,
+// microfacets can overshadow other microfacets which reduces reflected light.
+float geometrySmith(vec3 N, vec3 V, vec3 L, float roughness) {
+    float NdotV = max(dot(N, V), 0.0);
+    float NdotL = max(dot(N, L), 0.0);
+    float ggxL = geometrySchlickGGX(NdotL, roughness);
+    float ggxV = geometrySchlickGGX(NdotV, roughness);
+
+    return ggxL * ggxV;
+}
+
+// Describes the ratio of surface reflection at different surface angles.
+vec3 fresnelSchlick(float cosTheta, vec3 F0) {
+    return F0 + (1.0 - F0) * pow(1.0 - cosTheta, 5.0);
+}
+
+vec3 fresnelSchlickRoughness(float cosTheta, vec3 F0, float roughness) {
+    return F0 + (max(vec3(1.0 - roughness), F0) - F0) * pow(1.0 - cosTheta, 5.0);
+}
\ No newline at end of file
@@ -0,0 +1,15 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D source;
+uniform sampler2D bloom;
+uniform float intensity;
+
+void main()
+{
+    vec3 color = texture(source, TexCoords).rgb;
+    vec3 blurred = texture(bloom, TexCoords).rgb;
+
+    FragColor = vec4(mix(color, blurred, intensity), 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,69 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D source;
+uniform vec2 source_texel;
+
+// Only the first downsample removes light below the threshold
+uniform bool prefilter;
+uniform float threshold;
+uniform float knee;
+
+float luminance(vec3 color) {
+    return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
+
+// Quadratic soft knee around the threshold
+vec3 applyThreshold(vec3 color) {
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.00001);
+    float contribution = max(soft, brightness - threshold) / max(brightness, 0.00001);
+    return color * contribution;
+}
+
+// Weights a group of samples by inverse luminance so single bright pixels don't flicker
+vec3 karisAverage(vec3 a, vec3 b, vec3 c, vec3 d) {
+    float wa = 1.0 / (1.0 + luminance(a));
+    float wb = 1.0 / (1.0 + luminance(b));
+    float wc = 1.0 / (1.0 + luminance(c));
+    float wd = 1.0 / (1.0 + luminance(d));
+    return (a * wa + b * wb + c * wc + d * wd) / (wa + wb + wc + wd);
+}
+
+// 13 tap downsample from Call of Duty: Advanced Warfare
+void main()
+{
+    vec2 t = source_texel;
+    vec3 a = texture(source, TexCoords + t * vec2(-2.0, 2.0)).rgb;
+    vec3 b = texture(source, TexCoords + t * vec2(0.0, 2.0)).rgb;
+    vec3 c = texture(source, TexCoords + t * vec2(2.0, 2.0)).rgb;
+    vec3 d = texture(source, TexCoords + t * vec2(-2.0, 0.0)).rgb;
+    vec3 e = texture(source, TexCoords).rgb;
+    vec3 f = texture(source, TexCoords + t * vec2(2.0, 0.0)).rgb;
+    vec3 g = texture(source, TexCoords + t * vec2(-2.0, -2.0)).rgb;
+    vec3 h = texture(source, TexCoords + t * vec2(0.0, -2.0)).rgb;
+    vec3 i = texture(source, TexCoords + t * vec2(2.0, -2.0)).rgb;
+    vec3 j = texture(source, TexCoords + t * vec2(-1.0, 1.0)).rgb;
+    vec3 k = texture(source, TexCoords + t * vec2(1.0, 1.0)).rgb;
+    vec3 l = texture(source, TexCoords + t * vec2(-1.0, -1.0)).rgb;
+    vec3 m = texture(source, TexCoords + t * vec2(1.0, -1.0)).rgb;
+
+    vec3 color;
+    if (prefilter) {
+        color = karisAverage(j, k, l, m) * 0.5
+            + karisAverage(a, b, d, e) * 0.125
+            + karisAverage(b, c, e, f) * 0.125
+            + karisAverage(d, e, g, h) * 0.125
+            + karisAverage(e, f, h, i) * 0.125;
+        color = applyThreshold(color);
+    } else {
+        color = e * 0.125;
+        color += (a + c + g + i) * 0.03125;
+        color += (b + d + f + h) * 0.0625;
+        color += (j + k + l + m) * 0.125;
+    }
+
+    FragColor = vec4(max(color, vec3(0.0001)), 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,26 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D source;
+// Filter radius in texture coordinates
+uniform float radius;
+
+// 3x3 tent filter, the result is added onto the next larger mip
+void main()
+{
+    float x = radius;
+    float y = radius;
+
+    vec3 color = texture(source, TexCoords).rgb * 4.0;
+    color += (texture(source, TexCoords + vec2(-x, 0.0)).rgb
+        + texture(source, TexCoords + vec2(x, 0.0)).rgb
+        + texture(source, TexCoords + vec2(0.0, -y)).rgb
+        + texture(source, TexCoords + vec2(0.0, y)).rgb) * 2.0;
+    color += texture(source, TexCoords + vec2(-x, -y)).rgb
+        + texture(source, TexCoords + vec2(x, -y)).rgb
+        + texture(source, TexCoords + vec2(-x, y)).rgb
+        + texture(source, TexCoords + vec2(x, y)).rgb;
+
+    FragColor = vec4(color / 16.0, 1.0);
+}
\ No newline at end of file
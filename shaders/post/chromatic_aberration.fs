@@ -0,0 +1,18 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D source;
+uniform float intensity;
+
+// Red and blue are sampled further out and further in along the direction from the center
+void main()
+{
+    vec2 offset = (TexCoords - 0.5) * 2.0 * intensity;
+
+    float r = texture(source, TexCoords + offset).r;
+    float g = texture(source, TexCoords).g;
+    float b = texture(source, TexCoords - offset).b;
+
+    FragColor = vec4(r, g, b, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,19 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D source;
+uniform sampler3D lut;
+uniform float intensity;
+
+void main()
+{
+    vec3 color = clamp(texture(source, TexCoords).rgb, 0.0, 1.0);
+
+    // Map 0 and 1 to the centers of the first and last texels
+    float size = float(textureSize(lut, 0).x);
+    vec3 uvw = color * ((size - 1.0) / size) + 0.5 / size;
+    vec3 graded = texture(lut, uvw).rgb;
+
+    FragColor = vec4(mix(color, graded, intensity), 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,28 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D source;
+uniform float intensity;
+uniform float time;
+
+float hash(vec2 p) {
+    vec3 p3 = fract(vec3(p.xyx) * 0.1031);
+    p3 += dot(p3, p3.yzx + 33.33);
+    return fract((p3.x + p3.y) * p3.z);
+}
+
+void main()
+{
+    vec3 color = texture(source, TexCoords).rgb;
+
+    // New noise every frame, seeded by time
+    vec2 seed = gl_FragCoord.xy + fract(time * 13.0) * 1000.0;
+    float noise = hash(seed) - 0.5;
+
+    // Grain shows most in the midtones
+    float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    float response = 1.0 - abs(luminance * 2.0 - 1.0);
+
+    FragColor = vec4(color + noise * intensity * response, 1.0);
+}
\ No newline at end of file
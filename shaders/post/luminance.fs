@@ -0,0 +1,26 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D hdr_buffer;
+// Must match LUMINANCE_SIZE in src/engine/tonemap.rs
+const vec2 output_size = vec2(64.0);
+
+// Writes the average log2 luminance of a 4x4 grid of samples
+void main()
+{
+    vec2 footprint = 1.0 / output_size;
+    vec2 origin = TexCoords - footprint * 0.5;
+
+    float log_luminance = 0.0;
+    for (int x = 0; x < 4; x++) {
+        for (int y = 0; y < 4; y++) {
+            vec2 uv = origin + (vec2(x, y) + 0.5) * footprint / 4.0;
+            vec3 color = texture(hdr_buffer, uv).rgb;
+            float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+            log_luminance += log2(max(luminance, 0.00001));
+        }
+    }
+
+    FragColor = vec4(log_luminance / 16.0, 0.0, 0.0, 1.0);
+}
\ No newline at end of file
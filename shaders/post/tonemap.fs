@@ -0,0 +1,113 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D hdr_buffer;
+uniform float exposure;
+
+// Must match TonemapOperator in src/engine/tonemap.rs
+#define TONEMAP_REINHARD 0
+#define TONEMAP_ACES 1
+#define TONEMAP_UNCHARTED2 2
+#define TONEMAP_AGX 3
+uniform int operator;
+
+vec3 reinhard(vec3 color) {
+    return color / (color + vec3(1.0));
+}
+
+// Stephen Hill's fit of the ACES reference rendering and output transforms
+vec3 aces(vec3 color) {
+    const mat3 input_mat = mat3(
+        0.59719, 0.07600, 0.02840,
+        0.35458, 0.90834, 0.13383,
+        0.04823, 0.01566, 0.83777
+    );
+    const mat3 output_mat = mat3(
+         1.60475, -0.10208, -0.00327,
+        -0.53108,  1.10813, -0.07276,
+        -0.07367, -0.00605,  1.07602
+    );
+
+    color = input_mat * color;
+    vec3 a = color * (color + 0.0245786) - 0.000090537;
+    vec3 b = color * (0.983729 * color + 0.4329510) + 0.238081;
+    color = output_mat * (a / b);
+
+    return clamp(color, 0.0, 1.0);
+}
+
+// John Hable's filmic curve
+vec3 uncharted2Curve(vec3 x) {
+    const float A = 0.15;
+    const float B = 0.50;
+    const float C = 0.10;
+    const float D = 0.20;
+    const float E = 0.02;
+    const float F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 uncharted2(vec3 color) {
+    const float white_point = 11.2;
+    const float exposure_bias = 2.0;
+    return uncharted2Curve(color * exposure_bias) / uncharted2Curve(vec3(white_point));
+}
+
+// Polynomial fit of the AgX base contrast curve
+vec3 agxContrast(vec3 x) {
+    vec3 x2 = x * x;
+    vec3 x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x
+        + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+vec3 agx(vec3 color) {
+    const mat3 inset = mat3(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104
+    );
+    const mat3 outset = mat3(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116
+    );
+    const float min_ev = -12.47393;
+    const float max_ev = 4.026069;
+
+    color = inset * color;
+    color = clamp(log2(max(color, vec3(1e-10))), min_ev, max_ev);
+    color = (color - min_ev) / (max_ev - min_ev);
+    color = agxContrast(color);
+    color = outset * color;
+
+    // The curve outputs display encoded values, decode them so the
+    // sRGB encoding below applies to every operator alike
+    return pow(max(color, vec3(0.0)), vec3(2.2));
+}
+
+// Piecewise sRGB transfer function
+vec3 linearToSrgb(vec3 color) {
+    color = clamp(color, 0.0, 1.0);
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, vec3(lessThanEqual(color, vec3(0.0031308))));
+}
+
+void main()
+{
+    vec3 color = texture(hdr_buffer, TexCoords).rgb * exposure;
+
+    if (operator == TONEMAP_ACES) {
+        color = aces(color);
+    } else if (operator == TONEMAP_UNCHARTED2) {
+        color = uncharted2(color);
+    } else if (operator == TONEMAP_AGX) {
+        color = agx(color);
+    } else {
+        color = reinhard(color);
+    }
+
+    FragColor = vec4(linearToSrgb(color), 1.0);
+}
\ No newline at end of file
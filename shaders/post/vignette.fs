@@ -0,0 +1,19 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D source;
+uniform float intensity;
+uniform float radius;
+uniform float softness;
+
+void main()
+{
+    vec3 color = texture(source, TexCoords).rgb;
+
+    // 1 at the corners
+    float distance = length(TexCoords - 0.5) * sqrt(2.0);
+    float vignette = smoothstep(radius, radius - softness, distance);
+
+    FragColor = vec4(color * mix(1.0, vignette, intensity), 1.0);
+}
\ No newline at end of file
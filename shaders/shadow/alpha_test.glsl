@@ -0,0 +1,18 @@
+in vec2 TexCoords;
+
+// Same names as in shaders/pbr/geometry.fs, set by Material::use_with
+uniform float material_alpha;
+uniform float alpha_cutoff;
+uniform sampler2D albedo_map;
+uniform bool use_albedo_map;
+
+// Discards the parts an alpha tested material cuts out, they cast no shadow
+void alpha_test() {
+	float alpha = material_alpha;
+	if (use_albedo_map) {
+		alpha *= texture(albedo_map, TexCoords).a;
+	}
+	if (alpha < alpha_cutoff) {
+		discard;
+	}
+}
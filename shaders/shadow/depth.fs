@@ -0,0 +1,5 @@
+#version 330 core
+
+// Depth is written automatically
+void main() {
+}
\ No newline at end of file
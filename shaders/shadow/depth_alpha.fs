@@ -0,0 +1,7 @@
+#version 330 core
+#include "alpha_test.glsl"
+
+// Depth is written automatically
+void main() {
+	alpha_test();
+}
\ No newline at end of file
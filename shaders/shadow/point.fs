@@ -0,0 +1,10 @@
+#version 330 core
+in vec3 FragPos;
+
+uniform vec3 light_pos;
+uniform float far_plane;
+
+// Stores linear distance to the light, so any cube face can be compared the same way
+void main() {
+	gl_FragDepth = length(FragPos - light_pos) / far_plane;
+}
\ No newline at end of file
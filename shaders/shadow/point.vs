@@ -0,0 +1,17 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 2) in vec2 aTexCoords;
+
+uniform mat4 light_space;
+uniform mat4 model;
+
+out vec3 FragPos;
+out vec2 TexCoords;
+
+void main()
+{
+	vec4 worldPos = model * vec4(aPos, 1.0);
+	FragPos = worldPos.xyz;
+	TexCoords = aTexCoords;
+	gl_Position = light_space * worldPos;
+}
\ No newline at end of file
@@ -0,0 +1,13 @@
+#version 330 core
+in vec3 FragPos;
+
+#include "alpha_test.glsl"
+
+uniform vec3 light_pos;
+uniform float far_plane;
+
+// Stores linear distance to the light, like point.fs
+void main() {
+	alpha_test();
+	gl_FragDepth = length(FragPos - light_pos) / far_plane;
+}
\ No newline at end of file
@@ -0,0 +1,113 @@
+#version 330 core
+// Single scattering in a planet sized atmosphere, ray marched per pixel.
+// Distances are in meters.
+out vec4 FragColor;
+in vec3 Direction;
+
+// towards the sun
+uniform vec3 sun_direction;
+uniform float sun_intensity;
+// cosine of the angular radius of the sun disk
+uniform float sun_disk;
+
+uniform float planet_radius;
+uniform float atmosphere_radius;
+uniform float camera_height;
+
+uniform vec3 rayleigh_scattering;
+uniform float rayleigh_scale_height;
+uniform float mie_scattering;
+uniform float mie_scale_height;
+uniform float mie_g;
+
+const float PI = 3.14159265359;
+const int PRIMARY_STEPS = 16;
+const int LIGHT_STEPS = 8;
+
+// Near and far distance along the ray to a sphere around the planet center,
+// near is greater than far on a miss
+vec2 raySphere(vec3 origin, vec3 direction, float radius) {
+	float b = dot(origin, direction);
+	float c = dot(origin, origin) - radius * radius;
+	float d = b * b - c;
+	if (d < 0.0) {
+		return vec2(1e9, -1e9);
+	}
+	d = sqrt(d);
+	return vec2(-b - d, -b + d);
+}
+
+// Scattered light along the view ray, transmittance through it in the out parameter
+vec3 atmosphere(vec3 direction, out vec3 transmittance) {
+	transmittance = vec3(1.0);
+	vec3 origin = vec3(0.0, planet_radius + camera_height, 0.0);
+
+	vec2 hit = raySphere(origin, direction, atmosphere_radius);
+	if (hit.x > hit.y) {
+		return vec3(0.0);
+	}
+	float start = max(hit.x, 0.0);
+	float end = hit.y;
+
+	// Stop at the ground
+	vec2 ground = raySphere(origin, direction, planet_radius);
+	if (ground.x < ground.y && ground.x > 0.0) {
+		end = min(end, ground.x);
+	}
+
+	float step_size = (end - start) / float(PRIMARY_STEPS);
+	vec3 rayleigh = vec3(0.0);
+	vec3 mie = vec3(0.0);
+	float depth_rayleigh = 0.0;
+	float depth_mie = 0.0;
+
+	for (int i = 0; i < PRIMARY_STEPS; i++) {
+		vec3 position = origin + direction * (start + (float(i) + 0.5) * step_size);
+		float height = length(position) - planet_radius;
+
+		float density_rayleigh = exp(-height / rayleigh_scale_height) * step_size;
+		float density_mie = exp(-height / mie_scale_height) * step_size;
+		depth_rayleigh += density_rayleigh;
+		depth_mie += density_mie;
+
+		// Optical depth towards the sun
+		float light_step = raySphere(position, sun_direction, atmosphere_radius).y / float(LIGHT_STEPS);
+		float light_rayleigh = 0.0;
+		float light_mie = 0.0;
+		for (int j = 0; j < LIGHT_STEPS; j++) {
+			vec3 light_position = position + sun_direction * (float(j) + 0.5) * light_step;
+			float light_height = length(light_position) - planet_radius;
+			light_rayleigh += exp(-light_height / rayleigh_scale_height) * light_step;
+			light_mie += exp(-light_height / mie_scale_height) * light_step;
+		}
+
+		vec3 attenuation = exp(-(rayleigh_scattering * (depth_rayleigh + light_rayleigh)
+			+ mie_scattering * 1.1 * (depth_mie + light_mie)));
+		rayleigh += density_rayleigh * attenuation;
+		mie += density_mie * attenuation;
+	}
+
+	transmittance = exp(-(rayleigh_scattering * depth_rayleigh + mie_scattering * 1.1 * depth_mie));
+
+	float mu = dot(direction, sun_direction);
+	float g = mie_g;
+	float phase_rayleigh = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+	float phase_mie = 3.0 / (8.0 * PI) * ((1.0 - g * g) * (1.0 + mu * mu))
+		/ ((2.0 + g * g) * pow(1.0 + g * g - 2.0 * g * mu, 1.5));
+
+	return sun_intensity * (phase_rayleigh * rayleigh_scattering * rayleigh
+		+ phase_mie * mie_scattering * mie);
+}
+
+void main() {
+	vec3 direction = normalize(Direction);
+
+	vec3 transmittance;
+	vec3 color = atmosphere(direction, transmittance);
+
+	// Sun disk, dimmed by the air in front of it
+	float disk = smoothstep(sun_disk - 0.00002, sun_disk, dot(direction, sun_direction));
+	color += disk * sun_intensity * transmittance;
+
+	FragColor = vec4(color, 1.0);
+}
\ No newline at end of file
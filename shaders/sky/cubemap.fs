@@ -0,0 +1,13 @@
+#version 330 core
+out vec4 FragColor;
+in vec3 Direction;
+
+uniform samplerCube sky_map;
+uniform float intensity;
+// mip level sampled, blurs mipmapped maps
+uniform float lod;
+
+void main() {
+	vec3 color = textureLod(sky_map, normalize(Direction), lod).rgb;
+	FragColor = vec4(color * intensity, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,17 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+
+#include "../camera.glsl"
+
+// Normalized device z of the far plane, 0 for reversed depth
+uniform float far_ndc;
+
+out vec3 Direction;
+
+void main() {
+	Direction = aPos;
+	// Rotation only, so the sky stays around the camera
+	vec4 position = proj * mat4(mat3(view)) * vec4(aPos, 1.0);
+	// Far plane depth puts the sky behind everything
+	gl_Position = vec4(position.xy, position.w * far_ndc, position.w);
+}
\ No newline at end of file
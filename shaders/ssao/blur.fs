@@ -0,0 +1,23 @@
+#version 330 core
+// Drawn into the G-buffer, location 3 is g_material
+layout (location = 3) out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D ao;
+
+// Box blur over the size of the noise texture, removing its tiling pattern
+void main()
+{
+    vec2 texel = 1.0 / vec2(textureSize(ao, 0));
+
+    float result = 0.0;
+    for (int x = -2; x < 2; x++) {
+        for (int y = -2; y < 2; y++) {
+            vec2 offset = (vec2(x, y) + 0.5) * texel;
+            result += texture(ao, TexCoords + offset).r;
+        }
+    }
+
+    // Only the blue channel is written, it holds ao
+    FragColor = vec4(1.0, 1.0, result / 16.0, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,11 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D ao;
+
+void main()
+{
+    float value = texture(ao, TexCoords).r;
+    FragColor = vec4(value, value, value, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,57 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D g_position;
+uniform sampler2D g_normal;
+uniform sampler2D noise;
+
+// Must match MAX_SSAO_SAMPLES in src/engine/ssao.rs
+#define MAX_SAMPLES 64
+uniform vec3 samples[MAX_SAMPLES];
+uniform int sample_count;
+uniform float radius;
+uniform float bias;
+
+uniform mat4 view;
+uniform mat4 projection;
+// Screen size divided by the noise texture size
+uniform vec2 noise_scale;
+
+// Fraction of a view space hemisphere around the fragment that isn't inside geometry
+void main()
+{
+    vec3 world_normal = texture(g_normal, TexCoords).xyz;
+    if (dot(world_normal, world_normal) < 0.25) {
+        // Nothing was drawn here
+        FragColor = vec4(1.0);
+        return;
+    }
+
+    vec3 position = (view * vec4(texture(g_position, TexCoords).xyz, 1.0)).xyz;
+    vec3 normal = normalize(mat3(view) * world_normal);
+
+    // Random rotation around the normal, tiled over the screen
+    vec3 random = normalize(texture(noise, TexCoords * noise_scale).xyz);
+    vec3 tangent = normalize(random - normal * dot(random, normal));
+    vec3 bitangent = cross(normal, tangent);
+    mat3 TBN = mat3(tangent, bitangent, normal);
+
+    float occlusion = 0.0;
+    for (int i = 0; i < sample_count; i++) {
+        vec3 sample_pos = position + TBN * samples[i] * radius;
+
+        vec4 offset = projection * vec4(sample_pos, 1.0);
+        offset.xy = offset.xy / offset.w * 0.5 + 0.5;
+
+        vec3 surface = texture(g_position, offset.xy).xyz;
+        float surface_depth = (view * vec4(surface, 1.0)).z;
+
+        // Ignore geometry far outside the radius
+        float range = smoothstep(0.0, 1.0, radius / abs(position.z - surface_depth));
+        occlusion += (surface_depth >= sample_pos.z + bias ? 1.0 : 0.0) * range;
+    }
+
+    float ao = 1.0 - occlusion / float(sample_count);
+    FragColor = vec4(ao, ao, ao, 1.0);
+}
\ No newline at end of file
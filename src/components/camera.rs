@@ -1,38 +1,289 @@
-use nalgebra::{Matrix4, Perspective3, Point3};
+use nalgebra::{Matrix4, Orthographic3, Perspective3, Point3};
 
-use crate::components::Transform;
+use crate::components::{GlobalTransform, Transform};
+use crate::engine::color_target;
+use crate::wrapper::{
+	render::{buffers::FrameBuffer, core::Texture},
+	window::{DepthRange, Viewport},
+};
+
+/// Field of view in degrees, the other direction follows from the aspect ratio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fov {
+	Vertical(f32),
+	Horizontal(f32),
+}
+
+impl Fov {
+	/// Vertical field of view in radians for a width / height `aspect` ratio
+	pub fn vertical(&self, aspect: f32) -> f32 {
+		match *self {
+			Fov::Vertical(fov) => fov.to_radians(),
+			Fov::Horizontal(fov) => 2.0 * ((fov.to_radians() / 2.0).tan() / aspect).atan(),
+		}
+	}
+
+	/// Same kind of fov with a new value in degrees
+	pub fn with_degrees(&self, degrees: f32) -> Fov {
+		match self {
+			Fov::Vertical(_) => Fov::Vertical(degrees),
+			Fov::Horizontal(_) => Fov::Horizontal(degrees),
+		}
+	}
+
+	pub fn degrees(&self) -> f32 {
+		match *self {
+			Fov::Vertical(fov) | Fov::Horizontal(fov) => fov,
+		}
+	}
+}
+
+/// How a camera maps view space to clip space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+	Perspective {
+		fov: Fov,
+		near: f32,
+		far: f32,
+	},
+	/// Box of `height` world units, the width follows the aspect ratio
+	Orthographic {
+		height: f32,
+		near: f32,
+		far: f32,
+	},
+	/// Perspective without a far plane. Depth is reversed, 1 at the near plane
+	/// and 0 at infinity, which keeps float depth precise far away.
+	/// Passes drawing with it must use `depth_range`, which is standard depth
+	/// when the context can't reverse it.
+	InfinitePerspective {
+		fov: Fov,
+		near: f32,
+	},
+}
+
+impl Default for Projection {
+	fn default() -> Projection {
+		Projection::Perspective {
+			fov: Fov::Horizontal(90.0),
+			near: 0.1,
+			far: 100.0,
+		}
+	}
+}
+
+impl Projection {
+	pub fn matrix(&self, aspect: f32) -> Matrix4<f32> {
+		match *self {
+			Projection::Perspective { fov, near, far } => {
+				Perspective3::new(aspect, fov.vertical(aspect), near, far).to_homogeneous()
+			}
+			Projection::Orthographic { height, near, far } => {
+				let (x, y) = (height * aspect / 2.0, height / 2.0);
+				Orthographic3::new(-x, x, -y, y, near, far).to_homogeneous()
+			}
+			Projection::InfinitePerspective { fov, near } => {
+				let f = 1.0 / (fov.vertical(aspect) / 2.0).tan();
+				let mut matrix = Matrix4::zeros();
+				matrix[(0, 0)] = f / aspect;
+				matrix[(1, 1)] = f;
+				matrix[(3, 2)] = -1.0;
+				if DepthRange::reversed_supported() {
+					// Clip z is the near distance, so depth is near / distance
+					matrix[(2, 3)] = near;
+				} else {
+					// Standard perspective with the far plane moved to infinity
+					matrix[(2, 2)] = -1.0;
+					matrix[(2, 3)] = -2.0 * near;
+				}
+				matrix
+			}
+		}
+	}
+
+	pub fn near(&self) -> f32 {
+		match *self {
+			Projection::Perspective { near, .. }
+			| Projection::Orthographic { near, .. }
+			| Projection::InfinitePerspective { near, .. } => near,
+		}
+	}
+
+	/// Infinity for `InfinitePerspective`
+	pub fn far(&self) -> f32 {
+		match *self {
+			Projection::Perspective { far, .. } | Projection::Orthographic { far, .. } => far,
+			Projection::InfinitePerspective { .. } => f32::INFINITY,
+		}
+	}
+
+	/// The same projection cut off at `far` with standard depth.
+	/// Used where a finite frustum is needed, like fitting shadow cascades.
+	pub fn with_far(&self, far: f32) -> Projection {
+		match *self {
+			Projection::Perspective { fov, near, .. }
+			| Projection::InfinitePerspective { fov, near } => Projection::Perspective { fov, near, far },
+			Projection::Orthographic { height, near, .. } => {
+				Projection::Orthographic { height, near, far }
+			}
+		}
+	}
+
+	pub fn depth_range(&self) -> DepthRange {
+		match self {
+			Projection::InfinitePerspective { .. } if DepthRange::reversed_supported() => {
+				DepthRange::Reversed
+			}
+			_ => DepthRange::Standard,
+		}
+	}
+}
+
+/// Part of the output a camera draws into, from 0 to 1 starting at the bottom left.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewportRect {
+	pub x: f32,
+	pub y: f32,
+	pub width: f32,
+	pub height: f32,
+}
+
+impl Default for ViewportRect {
+	/// Whole output
+	fn default() -> ViewportRect {
+		ViewportRect {
+			x: 0.0,
+			y: 0.0,
+			width: 1.0,
+			height: 1.0,
+		}
+	}
+}
+
+impl ViewportRect {
+	/// x, y, width and height in pixels of an output `width` by `height` pixels
+	pub fn pixels(&self, width: u32, height: u32) -> (i32, i32, u32, u32) {
+		let (w, h) = (width as f32, height as f32);
+		let x = (self.x * w).round();
+		let y = (self.y * h).round();
+		let right = ((self.x + self.width) * w).round();
+		let top = ((self.y + self.height) * h).round();
+
+		(
+			x as i32,
+			y as i32,
+			(right - x).max(0.0) as u32,
+			(top - y).max(0.0) as u32,
+		)
+	}
+}
+
+/// Texture a camera renders into instead of the window.
+/// `texture` can be used in a `Material` to show what the camera sees.
+pub struct CameraTarget {
+	pub framebuffer: FrameBuffer,
+	pub texture: Texture,
+	pub width: u32,
+	pub height: u32,
+}
+
+impl CameraTarget {
+	pub fn new(width: u32, height: u32) -> Result<CameraTarget, String> {
+		// Holds the tonemapped image, sRGB so materials sample it as linear color
+		let framebuffer = color_target(width, height, gl::SRGB8_ALPHA8)?;
+		let texture = framebuffer.get_buffer("color").clone();
+
+		Ok(CameraTarget {
+			framebuffer,
+			texture,
+			width,
+			height,
+		})
+	}
+}
 
 pub struct Camera {
+	/// Degrees turned per pixel of mouse movement by camera controllers
 	pub mouse_sensitivity: f32,
-	pub fov: f32,
+	/// Call `update_projection` after changing it
+	pub projection: Projection,
+	/// Width / height, kept in sync with the output by `set_aspect_ratio`
 	pub aspect_ratio: f32,
 
-	pub projection: Matrix4<f32>,
+	/// Disabled cameras aren't rendered
+	pub enabled: bool,
+	/// Cameras are rendered from the lowest priority up, later ones draw over earlier ones.
+	/// Cameras rendering into a texture should come before the cameras that see it.
+	pub priority: i32,
+	/// Area of the output this camera draws into
+	pub viewport: ViewportRect,
+	/// Renders into this instead of the window when set
+	pub target: Option<CameraTarget>,
+
+	pub projection_matrix: Matrix4<f32>,
 	pub view: Matrix4<f32>,
 }
 
 impl Default for Camera {
 	fn default() -> Camera {
+		Camera::new(Projection::default())
+	}
+}
+
+impl Camera {
+	pub fn new(projection: Projection) -> Camera {
 		let mut cam = Camera {
 			mouse_sensitivity: 0.1,
-			fov: 90.0,
+			projection,
 			aspect_ratio: 1.0,
-			projection: Matrix4::default(),
-			view: Matrix4::default(),
+			enabled: true,
+			priority: 0,
+			viewport: ViewportRect::default(),
+			target: None,
+			projection_matrix: Matrix4::identity(),
+			view: Matrix4::identity(),
 		};
 		cam.update_projection();
 
 		cam
 	}
-}
 
-impl Camera {
 	pub fn update_projection(&mut self) {
-		let vertical = 2.0 * ((self.fov / 2.0).tan() * self.aspect_ratio).atan();
-		let projection: Perspective3<f32> =
-			Perspective3::new(self.aspect_ratio, vertical, 0.1, 100.0);
+		self.projection_matrix = self.projection.matrix(self.aspect_ratio);
+	}
+
+	/// Rebuilds the projection if the aspect ratio changed.
+	pub fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+		if aspect_ratio.is_finite() && aspect_ratio > 0.0 && aspect_ratio != self.aspect_ratio {
+			self.aspect_ratio = aspect_ratio;
+			self.update_projection();
+		}
+	}
+
+	/// Size in pixels of the texture target, or of the window without one
+	pub fn output_size(&self, window: &Viewport) -> (u32, u32) {
+		match &self.target {
+			Some(target) => (target.width, target.height),
+			None => (window.width, window.height),
+		}
+	}
+
+	/// Pixel rectangle of the output this camera draws into, see `ViewportRect::pixels`
+	pub fn output_rect(&self, window: &Viewport) -> (i32, i32, u32, u32) {
+		let (width, height) = self.output_size(window);
+		self.viewport.pixels(width, height)
+	}
 
-		self.projection = projection.to_homogeneous();
+	pub fn near(&self) -> f32 {
+		self.projection.near()
+	}
+
+	pub fn far(&self) -> f32 {
+		self.projection.far()
+	}
+
+	pub fn depth_range(&self) -> DepthRange {
+		self.projection.depth_range()
 	}
 
 	pub fn update_view(&mut self, tf: &Transform) {
@@ -41,4 +292,14 @@ impl Camera {
 
 		self.view = Matrix4::look_at_rh(pos, &sm, &tf.up);
 	}
+
+	/// Same as `update_view` but uses the world matrix,
+	/// so cameras attached to other entities follow them.
+	pub fn update_view_global(&mut self, global: &GlobalTransform) {
+		let position = global.position();
+		let pos: &Point3<f32> = &position.into();
+		let sm: Point3<f32> = (position + global.forward()).into();
+
+		self.view = Matrix4::look_at_rh(pos, &sm, &global.up());
+	}
 }
@@ -0,0 +1,89 @@
+use glfw::MouseButton;
+use nalgebra::Vector3;
+
+/// Free flying camera, moved by the "move_forward", "move_right" and
+/// "move_up" axes and turned with the mouse and the right stick of any gamepad.
+/// Mouse sensitivity is `Camera::mouse_sensitivity` in degrees per pixel.
+pub struct FlyCameraController {
+	/// Units per second
+	pub speed: f32,
+	/// Speed factor while the "sprint" action is held
+	pub sprint_multiplier: f32,
+	/// Degrees per second of the right stick at full deflection
+	pub look_speed: f32,
+	pub stick_dead_zone: f32,
+	/// Mouse look only happens while this button is held, always when None
+	pub look_button: Option<MouseButton>,
+
+	/// Pitch limits in degrees, positive looks down
+	pub min_pitch: f32,
+	pub max_pitch: f32,
+
+	/// Degrees of field of view per scroll step of the "zoom" axis
+	pub zoom_speed: f32,
+	pub min_fov: f32,
+	pub max_fov: f32,
+	/// Fraction of the height of orthographic cameras per scroll step
+	pub ortho_zoom_speed: f32,
+}
+
+impl Default for FlyCameraController {
+	fn default() -> FlyCameraController {
+		FlyCameraController {
+			speed: 5.0,
+			sprint_multiplier: 3.0,
+			look_speed: 120.0,
+			stick_dead_zone: 0.1,
+			look_button: Some(MouseButton::Button2),
+			min_pitch: -89.0,
+			max_pitch: 89.0,
+			zoom_speed: 5.0,
+			min_fov: 20.0,
+			max_fov: 110.0,
+			ortho_zoom_speed: 0.1,
+		}
+	}
+}
+
+/// Camera circling a target point, turned by dragging the mouse and
+/// the right stick of any gamepad, moved closer with the "zoom" axis.
+/// Mouse sensitivity is `Camera::mouse_sensitivity` in degrees per pixel.
+pub struct OrbitCameraController {
+	pub target: Vector3<f32>,
+	pub distance: f32,
+	pub min_distance: f32,
+	pub max_distance: f32,
+	/// Fraction of the distance moved per scroll step
+	pub zoom_speed: f32,
+
+	/// Angles around the target in degrees, positive pitch looks down
+	pub yaw: f32,
+	pub pitch: f32,
+	pub min_pitch: f32,
+	pub max_pitch: f32,
+
+	/// Degrees per second of the right stick at full deflection
+	pub look_speed: f32,
+	pub stick_dead_zone: f32,
+	/// Dragging with this button turns the camera, always turns when None
+	pub rotate_button: Option<MouseButton>,
+}
+
+impl Default for OrbitCameraController {
+	fn default() -> OrbitCameraController {
+		OrbitCameraController {
+			target: Vector3::zeros(),
+			distance: 5.0,
+			min_distance: 0.5,
+			max_distance: 50.0,
+			zoom_speed: 0.1,
+			yaw: 0.0,
+			pitch: 30.0,
+			min_pitch: -89.0,
+			max_pitch: 89.0,
+			look_speed: 120.0,
+			stick_dead_zone: 0.1,
+			rotate_button: Some(MouseButton::Button1),
+		}
+	}
+}
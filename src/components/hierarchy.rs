@@ -0,0 +1,37 @@
+use legion::Entity;
+use nalgebra::{Matrix4, Vector3};
+
+/// Entity this entity's transform is relative to.
+pub struct Parent(pub Entity);
+
+/// Entities whose transforms are relative to this entity.
+#[derive(Default)]
+pub struct Children(pub Vec<Entity>);
+
+/// Cached world matrix, kept up to date by the transform propagation system.
+#[derive(Clone)]
+pub struct GlobalTransform {
+	pub matrix: Matrix4<f32>,
+}
+
+impl Default for GlobalTransform {
+	fn default() -> GlobalTransform {
+		GlobalTransform {
+			matrix: Matrix4::identity(),
+		}
+	}
+}
+
+impl GlobalTransform {
+	pub fn position(&self) -> Vector3<f32> {
+		self.matrix.column(3).xyz()
+	}
+
+	pub fn forward(&self) -> Vector3<f32> {
+		self.matrix.transform_vector(&Vector3::z()).normalize()
+	}
+
+	pub fn up(&self) -> Vector3<f32> {
+		self.matrix.transform_vector(&Vector3::y()).normalize()
+	}
+}
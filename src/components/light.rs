@@ -1,18 +1,166 @@
-use nalgebra::Vector3;
+use crate::components::{Fov, Projection};
+use crate::util::cube_face_views;
+use nalgebra::{Matrix4, Point3, Vector3};
+
+/// Shape of the light emitted by a `Light`.
+/// Spot and directional lights shine along the transform's forward vector.
+#[derive(Clone, Copy)]
+pub enum LightKind {
+	Point,
+	/// Cone angles are in radians, measured from the center of the cone.
+	/// Light fades out between the inner and outer angle.
+	Spot {
+		inner_angle: f32,
+		outer_angle: f32,
+	},
+	Directional,
+}
+
+/// Must match MAX_CASCADES in shaders/pbr/lighting.glsl
+pub const MAX_CASCADES: usize = 4;
+
+/// Splits the camera frustum into several shadow maps for directional lights.
+#[derive(Clone, Copy)]
+pub struct Cascades {
+	pub count: usize,
+	/// View space distance where each cascade ends.
+	pub splits: [f32; MAX_CASCADES],
+}
+
+impl Cascades {
+	/// Blends logarithmic and uniform splits between `near` and `far`.
+	/// `lambda` 1.0 is fully logarithmic, 0.0 fully uniform.
+	pub fn practical(count: usize, near: f32, far: f32, lambda: f32) -> Cascades {
+		let count = count.clamp(1, MAX_CASCADES);
+		let mut splits = [far; MAX_CASCADES];
+		for (i, split) in splits.iter_mut().enumerate().take(count) {
+			let p = (i + 1) as f32 / count as f32;
+			let log = near * (far / near).powf(p);
+			let uniform = near + (far - near) * p;
+			*split = lambda * log + (1.0 - lambda) * uniform;
+		}
+
+		Cascades { count, splits }
+	}
+}
+
+/// Shadow map settings for a light.
+#[derive(Clone, Copy)]
+pub struct ShadowSettings {
+	/// Width and height of the shadow map in texels.
+	pub resolution: u32,
+	/// Depth offset to avoid shadow acne.
+	pub bias: f32,
+	/// Half size of the area covered by a directional light's shadow map,
+	/// centered on the light's position.
+	pub extent: f32,
+	pub near: f32,
+	pub far: f32,
+	/// Directional lights only, replaces `extent` with cascades fitted to the camera.
+	pub cascades: Option<Cascades>,
+}
+
+impl Default for ShadowSettings {
+	fn default() -> ShadowSettings {
+		ShadowSettings {
+			resolution: 1024,
+			bias: 0.005,
+			extent: 10.0,
+			near: 0.1,
+			far: 50.0,
+			cascades: None,
+		}
+	}
+}
 
 pub struct Light {
+	pub kind: LightKind,
 	pub color: Vector3<f32>,
+	pub intensity: f32,
+
+	/// Distance at which point and spot lights fade to zero.
+	pub range: f32,
 
-	pub quadratic: f32,
-	pub linear: f32,
+	/// Casts shadows when set.
+	pub shadow: Option<ShadowSettings>,
 }
 
 impl Default for Light {
 	fn default() -> Light {
 		Light {
+			kind: LightKind::Point,
 			color: Vector3::default(),
-			quadratic: 1.8,
-			linear: 0.7,
+			intensity: 1.0,
+			range: 10.0,
+			shadow: None,
 		}
 	}
 }
+
+impl Light {
+	/// Light space matrices for each cube map face of a point light,
+	/// ordered +X, -X, +Y, -Y, +Z, -Z. The far plane is clamped to the light's range.
+	pub fn cube_matrices(&self, position: Vector3<f32>) -> Option<[Matrix4<f32>; 6]> {
+		let shadow = self.shadow?;
+		if !matches!(self.kind, LightKind::Point) {
+			return None;
+		}
+
+		let far = shadow.far.min(self.range);
+		let projection = Projection::Perspective {
+			fov: Fov::Vertical(90.0),
+			near: shadow.near,
+			far,
+		}
+		.matrix(1.0);
+
+		let views = cube_face_views(&position.into());
+		let matrices = views.map(|view| projection * view);
+
+		Some(matrices)
+	}
+
+	/// Projection * view matrix used to render and sample the shadow map.
+	/// Returns None for lights without shadows or without a single direction.
+	pub fn light_space_matrix(
+		&self,
+		position: Vector3<f32>,
+		direction: Vector3<f32>,
+	) -> Option<Matrix4<f32>> {
+		let shadow = self.shadow?;
+
+		let projection = match self.kind {
+			LightKind::Point => return None,
+			LightKind::Spot { outer_angle, .. } => Projection::Perspective {
+				fov: Fov::Vertical((outer_angle * 2.0).to_degrees()),
+				near: shadow.near,
+				far: shadow.far.min(self.range),
+			},
+			LightKind::Directional => Projection::Orthographic {
+				height: shadow.extent * 2.0,
+				near: shadow.near,
+				far: shadow.far,
+			},
+		}
+		.matrix(1.0);
+
+		// Directional shadows start behind the covered area
+		let eye = match self.kind {
+			LightKind::Directional => position - direction * shadow.far * 0.5,
+			_ => position,
+		};
+
+		// Pick an up vector that isn't parallel to the light direction
+		let up = if direction.normalize().y.abs() > 0.99 {
+			Vector3::z()
+		} else {
+			Vector3::y()
+		};
+
+		let eye: Point3<f32> = eye.into();
+		let target = eye + direction;
+		let view = Matrix4::look_at_rh(&eye, &target, &up);
+
+		Some(projection * view)
+	}
+}
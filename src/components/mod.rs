@@ -1,9 +1,13 @@
 mod camera;
+mod camera_controller;
+mod hierarchy;
 mod light;
 mod renderable;
 mod transform;
 
 pub use camera::*;
+pub use camera_controller::*;
+pub use hierarchy::*;
 pub use light::*;
 pub use renderable::*;
 pub use transform::*;
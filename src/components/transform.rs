@@ -1,6 +1,7 @@
 use glm;
 use nalgebra::{vector, Matrix4, Rotation3, Vector3};
 
+#[derive(Clone)]
 pub struct Transform {
 	pub position: Vector3<f32>,
 	pub rotation: Rotation3<f32>,
@@ -14,7 +15,7 @@ pub struct Transform {
 impl Default for Transform {
 	fn default() -> Transform {
 		Transform {
-			position: Vector3::identity(),
+			position: Vector3::zeros(),
 			rotation: Rotation3::identity(),
 			scale: vector!(1.0, 1.0, 1.0),
 			forward: Vector3::z(),
@@ -0,0 +1,410 @@
+use legion::{systems::Builder, *};
+use std::time::Instant;
+
+use crate::engine::RenderTargets;
+use crate::wrapper::{
+	error::error_callback,
+	window::{Time, VSync, Window, WindowMode, WindowSettings},
+};
+
+/// Fixed timestep of the fixed update schedule, a resource.
+/// Fixed systems advance the simulation by `step` seconds every run,
+/// independent of the frame rate.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedTime {
+	/// Seconds of game time per fixed update
+	pub step: f32,
+	/// Fixed updates run at most per frame. Time beyond that is dropped,
+	/// so one slow frame can't cause ever longer catch ups.
+	pub max_steps: u32,
+
+	accumulator: f32,
+	steps: u32,
+}
+
+impl Default for FixedTime {
+	/// 60 updates per second
+	fn default() -> FixedTime {
+		FixedTime::new(1.0 / 60.0)
+	}
+}
+
+impl FixedTime {
+	pub fn new(step: f32) -> FixedTime {
+		FixedTime {
+			step,
+			max_steps: 5,
+			accumulator: 0.0,
+			steps: 0,
+		}
+	}
+
+	/// How far game time is between the last fixed update and the next, from 0 to 1.
+	/// Rendering can blend the last two fixed states by it for smooth motion.
+	pub fn alpha(&self) -> f32 {
+		(self.accumulator / self.step).clamp(0.0, 1.0)
+	}
+
+	/// Fixed updates run this frame
+	pub fn steps(&self) -> u32 {
+		self.steps
+	}
+
+	/// Adds `delta_time` seconds of game time and returns the updates to run.
+	fn accumulate(&mut self, delta_time: f32) -> u32 {
+		if self.step <= 0.0 {
+			self.steps = 0;
+			return 0;
+		}
+
+		self.accumulator += delta_time;
+		let steps = ((self.accumulator / self.step) as u32).min(self.max_steps);
+		self.accumulator -= steps as f32 * self.step;
+		if self.accumulator >= self.step {
+			self.accumulator %= self.step;
+		}
+
+		self.steps = steps;
+		steps
+	}
+}
+
+/// Resource, set `requested` to stop `App::run` after the current frame.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AppExit {
+	pub requested: bool,
+}
+
+/// Resource with the window events of this frame, empty when headless.
+#[derive(Default)]
+pub struct WindowEvents(pub Vec<glfw::WindowEvent>);
+
+/// Resource for systems to change the window, applied before the render schedule.
+/// The current mode is the `WindowMode` resource.
+#[derive(Clone, Debug, Default)]
+pub struct WindowRequests {
+	pub mode: Option<WindowMode>,
+	pub vsync: Option<VSync>,
+}
+
+/// Adds resources, systems and startup work to an `App`.
+/// Plugins are built in the order they are added, systems added to
+/// the same stage run in that order too.
+pub trait Plugin {
+	fn build(&self, app: &mut App);
+}
+
+/// Functions taking the app are plugins, for setup that needs no settings.
+impl<F: Fn(&mut App)> Plugin for F {
+	fn build(&self, app: &mut App) {
+		self(app)
+	}
+}
+
+/// Resource with the errors of the `WindowPlugin`, for the game to show or log.
+#[derive(Debug, Default)]
+pub struct WindowErrors {
+	/// The icon of `WindowSettings::icon` could not be loaded, the window has none
+	pub icon: Option<image::ImageError>,
+}
+
+/// Opens the window and its OpenGL context.
+/// Add it before plugins that render or load GPU assets.
+/// Failures to load the icon end up in the `WindowErrors` resource.
+#[derive(Clone, Debug, Default)]
+pub struct WindowPlugin {
+	pub settings: WindowSettings,
+}
+
+impl Plugin for WindowPlugin {
+	fn build(&self, app: &mut App) {
+		let mut window = Window::new(self.settings.clone()).default_setup();
+		let mut errors = WindowErrors::default();
+		if let Some(icon) = &self.settings.icon {
+			errors.icon = window.set_icon(icon).err();
+		}
+		if window.settings.debug_context {
+			window.debug_message_callback(Some(error_callback));
+		}
+
+		app.resources.insert(window.get_viewport());
+		app.resources.insert(window.settings.mode);
+		app.resources.insert(errors);
+		app.window = Some(window);
+	}
+}
+
+struct Schedules {
+	pre_update: Schedule,
+	fixed_update: Schedule,
+	update: Schedule,
+	post_update: Schedule,
+	render: Schedule,
+}
+
+/// Owns the window, world and resources and runs the game loop.
+/// Each frame runs `pre_update`, then `fixed_update` as often as `FixedTime` says,
+/// then `update`, `post_update` and `render` once.
+pub struct App {
+	pub world: World,
+	pub resources: Resources,
+	/// None when headless, opened by `WindowPlugin`
+	pub window: Option<Window>,
+
+	/// Runs once before the first frame, for spawning the initial entities
+	pub startup: Builder,
+	/// Reads the events of the frame, like input
+	pub pre_update: Builder,
+	/// Gameplay and physics, reads `FixedTime::step`
+	pub fixed_update: Builder,
+	/// Per frame logic like camera controllers, reads `Time::delta_time`
+	pub update: Builder,
+	/// Follows up on the moved entities, like transform propagation and cameras
+	pub post_update: Builder,
+	/// Drawing, only runs with a window
+	pub render: Builder,
+
+	/// Built from the builders on the first frame, systems added later are ignored
+	schedules: Option<Schedules>,
+}
+
+impl Default for App {
+	fn default() -> App {
+		App::new()
+	}
+}
+
+impl App {
+	/// Creates an app without a window, add a `WindowPlugin` to open one.
+	/// Headless apps skip the render schedule, for tests and servers
+	/// drive them with `frame` or stop `run` with `AppExit`.
+	pub fn new() -> App {
+		let mut resources = Resources::default();
+		resources.insert(Time::default());
+		resources.insert(FixedTime::default());
+		resources.insert(AppExit::default());
+		resources.insert(WindowEvents::default());
+		resources.insert(WindowRequests::default());
+
+		App {
+			world: World::default(),
+			resources,
+			window: None,
+			startup: Schedule::builder(),
+			pre_update: Schedule::builder(),
+			fixed_update: Schedule::builder(),
+			update: Schedule::builder(),
+			post_update: Schedule::builder(),
+			render: Schedule::builder(),
+			schedules: None,
+		}
+	}
+
+	pub fn add_plugin(&mut self, plugin: impl Plugin) -> &mut Self {
+		plugin.build(self);
+		self
+	}
+
+	/// Runs `f` once before the first frame, after every plugin is built.
+	pub fn add_startup(
+		&mut self,
+		f: impl FnMut(&mut World, &mut Resources) + 'static,
+	) -> &mut Self {
+		self.startup.add_thread_local_fn(f);
+		self
+	}
+
+	/// Runs frames until the window closes or `AppExit` is requested.
+	/// Headless apps only stop through `AppExit`.
+	pub fn run(&mut self) {
+		let mut last = Instant::now();
+		loop {
+			let now = Instant::now();
+			self.frame((now - last).as_secs_f32());
+			last = now;
+
+			let exit = self.resources.get::<AppExit>().unwrap().requested;
+			let closed = match &self.window {
+				Some(window) => window.should_close(),
+				None => false,
+			};
+			if exit || closed {
+				break;
+			}
+		}
+	}
+
+	/// Runs one frame as if `real_delta` seconds passed since the last one.
+	pub fn frame(&mut self, real_delta: f32) {
+		if self.schedules.is_none() {
+			self.startup
+				.build()
+				.execute(&mut self.world, &mut self.resources);
+			self.schedules = Some(Schedules {
+				pre_update: self.pre_update.build(),
+				fixed_update: self.fixed_update.build(),
+				update: self.update.build(),
+				post_update: self.post_update.build(),
+				render: self.render.build(),
+			});
+		}
+		let schedules = self.schedules.as_mut().unwrap();
+		let (world, resources) = (&mut self.world, &mut self.resources);
+
+		// Events of the last frame, the window sees resizes first
+		let mut events = Vec::new();
+		if let Some(window) = &mut self.window {
+			events.extend(window.events());
+			for event in &events {
+				window.handle_event(event);
+			}
+
+			let viewport = window.get_viewport();
+			resources.insert(viewport);
+			resources.insert(window.settings.mode);
+			RenderTargets::resize(resources, viewport);
+		}
+		resources.insert(WindowEvents(events));
+
+		let delta_time = {
+			let mut time = resources.get_mut::<Time>().unwrap();
+			time.advance(real_delta);
+			time.delta_time
+		};
+		schedules.pre_update.execute(world, resources);
+
+		let steps = resources
+			.get_mut::<FixedTime>()
+			.unwrap()
+			.accumulate(delta_time);
+		for _ in 0..steps {
+			schedules.fixed_update.execute(world, resources);
+		}
+
+		schedules.update.execute(world, resources);
+		schedules.post_update.execute(world, resources);
+
+		let window = match &mut self.window {
+			Some(window) => window,
+			None => return,
+		};
+
+		let requests = std::mem::take(&mut *resources.get_mut::<WindowRequests>().unwrap());
+		if let Some(mode) = requests.mode {
+			window.set_mode(mode);
+		}
+		if let Some(vsync) = requests.vsync {
+			window.set_vsync(vsync);
+		}
+
+		schedules.render.execute(world, resources);
+		window.post_loop();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Fixed updates run so far
+	struct FixedRuns(u32);
+
+	/// Headless app with a `step` of `FixedTime` that counts its fixed updates
+	fn counting_app(step: f32) -> App {
+		let mut app = App::new();
+		app.resources.insert(FixedTime::new(step));
+		app.resources.insert(FixedRuns(0));
+		app.fixed_update.add_thread_local_fn(|_, resources| {
+			resources.get_mut::<FixedRuns>().unwrap().0 += 1;
+		});
+		app
+	}
+
+	fn fixed_runs(app: &App) -> u32 {
+		app.resources.get::<FixedRuns>().unwrap().0
+	}
+
+	fn fixed_time(app: &App) -> FixedTime {
+		*app.resources.get::<FixedTime>().unwrap()
+	}
+
+	#[test]
+	fn fixed_steps_follow_delta_time() {
+		let mut app = counting_app(0.25);
+
+		app.frame(0.625);
+		assert_eq!(fixed_runs(&app), 2);
+		assert_eq!(fixed_time(&app).steps(), 2);
+		assert_eq!(fixed_time(&app).alpha(), 0.5);
+
+		// The leftover half step completes with this frame
+		app.frame(0.125);
+		assert_eq!(fixed_runs(&app), 3);
+		assert_eq!(fixed_time(&app).steps(), 1);
+		assert_eq!(fixed_time(&app).alpha(), 0.0);
+
+		app.frame(0.0625);
+		assert_eq!(fixed_runs(&app), 3);
+		assert_eq!(fixed_time(&app).steps(), 0);
+		assert_eq!(fixed_time(&app).alpha(), 0.25);
+	}
+
+	#[test]
+	fn max_steps_drops_extra_time() {
+		let mut app = counting_app(0.25);
+		app.resources.get_mut::<FixedTime>().unwrap().max_steps = 2;
+
+		app.frame(2.0);
+		assert_eq!(fixed_runs(&app), 2);
+		assert_eq!(fixed_time(&app).alpha(), 0.0);
+
+		// Nothing of the dropped time is caught up later
+		app.frame(0.125);
+		assert_eq!(fixed_runs(&app), 2);
+		assert_eq!(fixed_time(&app).alpha(), 0.5);
+	}
+
+	#[test]
+	fn time_scale_and_pause_change_game_time() {
+		let mut app = counting_app(0.25);
+		app.resources.get_mut::<Time>().unwrap().time_scale = 0.5;
+
+		app.frame(1.0);
+		{
+			let time = app.resources.get::<Time>().unwrap();
+			assert_eq!(time.real_delta_time, 1.0);
+			assert_eq!(time.delta_time, 0.5);
+			assert_eq!(time.elapsed, 0.5);
+		}
+		assert_eq!(fixed_runs(&app), 2);
+
+		app.resources.get_mut::<Time>().unwrap().paused = true;
+		app.frame(1.0);
+		{
+			let time = app.resources.get::<Time>().unwrap();
+			assert_eq!(time.real_delta_time, 1.0);
+			assert_eq!(time.delta_time, 0.0);
+			assert_eq!(time.elapsed, 0.5);
+			assert_eq!(time.frame_count, 2);
+		}
+		assert_eq!(fixed_runs(&app), 2);
+	}
+
+	#[test]
+	fn app_exit_stops_run() {
+		struct Frames(u32);
+
+		let mut app = App::new();
+		app.resources.insert(Frames(0));
+		app.update.add_thread_local_fn(|_, resources| {
+			let mut frames = resources.get_mut::<Frames>().unwrap();
+			frames.0 += 1;
+			if frames.0 == 3 {
+				resources.get_mut::<AppExit>().unwrap().requested = true;
+			}
+		});
+
+		app.run();
+		assert_eq!(app.resources.get::<Frames>().unwrap().0, 3);
+	}
+}
@@ -0,0 +1,56 @@
+use crate::engine::{App, Environment, Plugin};
+use crate::wrapper::render::core::MaterialManager;
+
+/// Loads assets shared by the whole game into resources:
+/// material files into `MaterialManager` and an HDR image into `Environment`.
+/// Needs the `WindowPlugin` when there is something to load.
+#[derive(Clone, Debug)]
+pub struct AssetsPlugin {
+	/// Material files, see materials/green.ron
+	pub materials: Vec<String>,
+	/// Image lighting the scene, the `RenderPlugin` also shows it as sky
+	pub environment: Option<String>,
+	/// Precomputed environment maps are cached here so later starts skip the convolution
+	pub cache_dir: Option<String>,
+}
+
+impl Default for AssetsPlugin {
+	fn default() -> AssetsPlugin {
+		AssetsPlugin {
+			materials: Vec::new(),
+			environment: None,
+			cache_dir: Some(".cache/ibl".to_owned()),
+		}
+	}
+}
+
+impl Plugin for AssetsPlugin {
+	fn build(&self, app: &mut App) {
+		let mut materials = MaterialManager::new();
+		for path in &self.materials {
+			if let Err(e) = materials.load(path) {
+				panic!("Material: {}", e);
+			}
+		}
+		app.resources.insert(materials);
+
+		if let Some(path) = &self.environment {
+			let window = app
+				.window
+				.as_ref()
+				.expect("AssetsPlugin needs a window to load the environment");
+			let viewport = window.get_viewport();
+			let environment = match Environment::load(
+				path,
+				self.cache_dir.as_deref(),
+				(viewport.width, viewport.height),
+			) {
+				Ok(e) => e,
+				Err(e) => {
+					panic!("Environment: {}", e);
+				}
+			};
+			app.resources.insert(environment);
+		}
+	}
+}
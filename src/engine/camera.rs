@@ -0,0 +1,217 @@
+use legion::{world::SubWorld, *};
+use nalgebra::{Matrix4, Vector3};
+use std::mem::size_of;
+
+use crate::components::{Camera, GlobalTransform, Transform};
+use crate::wrapper::{
+	error::GLError,
+	render::{buffers::UniformBuffer, core::shader::Shader},
+	window::{DepthRange, Viewport},
+};
+
+/// The `Camera` uniform block, std140 layout.
+/// Must match shaders/camera.glsl
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct CameraData {
+	/// Column major matrices
+	pub projection: [f32; 16],
+	pub view: [f32; 16],
+	pub inverse_projection: [f32; 16],
+	pub inverse_view: [f32; 16],
+	/// xyz world position, w near plane
+	pub position: [f32; 4],
+}
+
+/// Framebuffer and pixel rectangle a camera presents its image to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CameraOutput {
+	/// 0 for the window
+	pub fbo: u32,
+	pub x: i32,
+	pub y: i32,
+	pub width: u32,
+	pub height: u32,
+}
+
+impl CameraOutput {
+	/// Binds the framebuffer and sets the viewport to the rectangle.
+	pub fn bind(&self) {
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+			gl::Viewport(self.x, self.y, self.width as i32, self.height as i32);
+		}
+	}
+}
+
+/// A camera as seen by the render passes.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraView {
+	pub entity: Entity,
+	pub priority: i32,
+	pub output: CameraOutput,
+	pub position: Vector3<f32>,
+	pub view: Matrix4<f32>,
+	pub projection: Matrix4<f32>,
+	pub inverse_view: Matrix4<f32>,
+	pub inverse_projection: Matrix4<f32>,
+	pub near: f32,
+	pub depth: DepthRange,
+}
+
+impl CameraView {
+	/// Expects the view and projection of `camera` to be up to date.
+	pub fn new(entity: Entity, camera: &Camera, window: &Viewport) -> CameraView {
+		let inverse_view = camera.view.try_inverse().unwrap_or_else(Matrix4::identity);
+		let inverse_projection = camera
+			.projection_matrix
+			.try_inverse()
+			.unwrap_or_else(Matrix4::identity);
+
+		let (x, y, width, height) = camera.output_rect(window);
+		let fbo = match &camera.target {
+			Some(target) => target.framebuffer.fbo,
+			None => 0,
+		};
+
+		CameraView {
+			entity,
+			priority: camera.priority,
+			output: CameraOutput {
+				fbo,
+				x,
+				y,
+				width,
+				height,
+			},
+			position: inverse_view.column(3).xyz(),
+			view: camera.view,
+			projection: camera.projection_matrix,
+			inverse_view,
+			inverse_projection,
+			near: camera.near(),
+			depth: camera.depth_range(),
+		}
+	}
+
+	pub fn data(&self) -> CameraData {
+		let mut data = CameraData {
+			position: [self.position.x, self.position.y, self.position.z, self.near],
+			..CameraData::default()
+		};
+		data.projection.copy_from_slice(self.projection.as_slice());
+		data.view.copy_from_slice(self.view.as_slice());
+		data.inverse_projection
+			.copy_from_slice(self.inverse_projection.as_slice());
+		data.inverse_view
+			.copy_from_slice(self.inverse_view.as_slice());
+
+		data
+	}
+}
+
+/// Resource with the cameras of this frame, collected by `collect_cameras`.
+/// The renderer activates each one in turn and runs every pass for it.
+#[derive(Default)]
+pub struct ActiveCamera {
+	views: Vec<CameraView>,
+	view: Option<CameraView>,
+}
+
+impl ActiveCamera {
+	/// Enabled cameras in render order
+	pub fn views(&self) -> &[CameraView] {
+		&self.views
+	}
+
+	/// Camera being rendered, None before the first `activate` of a frame
+	pub fn view(&self) -> Option<&CameraView> {
+		self.view.as_ref()
+	}
+
+	/// Makes passes render from `view` and uploads it to `buffer`.
+	pub fn activate(&mut self, view: CameraView, buffer: &CameraBuffer) {
+		buffer.upload(&view);
+		self.view = Some(view);
+	}
+
+	/// Camera component of the camera being rendered
+	pub fn camera<'a>(&self, world: &'a World) -> Option<&'a Camera> {
+		let entity = self.view?.entity;
+		world
+			.entry_ref(entity)
+			.ok()?
+			.into_component::<Camera>()
+			.ok()
+	}
+
+	/// Depth range of the camera being rendered, standard without one
+	pub fn depth_range(&self) -> DepthRange {
+		match &self.view {
+			Some(view) => view.depth,
+			None => DepthRange::Standard,
+		}
+	}
+}
+
+/// Uniform buffer holding the camera being rendered, shared by every pass.
+pub struct CameraBuffer {
+	ubo: UniformBuffer,
+	bind_point: u32,
+}
+
+impl CameraBuffer {
+	pub fn new(bind_point: u32) -> Result<CameraBuffer, GLError> {
+		let ubo = UniformBuffer::create_buffer(bind_point, size_of::<CameraData>())?;
+
+		Ok(CameraBuffer { ubo, bind_point })
+	}
+
+	/// Connects the `Camera` block of `shader` to this buffer.
+	pub fn bind(&self, shader: &Shader) {
+		UniformBuffer::set_uniform_block(shader, "Camera", self.bind_point);
+	}
+
+	pub fn upload(&self, view: &CameraView) {
+		self.ubo.set_data(0, &[view.data()]);
+	}
+}
+
+/// Keeps every camera's view on its transform and its aspect ratio on its output.
+#[system(for_each)]
+pub fn update_cameras(
+	tf: &mut Transform,
+	global: Option<&GlobalTransform>,
+	cam: &mut Camera,
+	#[resource] viewport: &Viewport,
+) {
+	tf.update_directions();
+	match global {
+		Some(global) => cam.update_view_global(global),
+		None => cam.update_view(tf),
+	}
+
+	let (_, _, width, height) = cam.output_rect(viewport);
+	cam.set_aspect_ratio(Viewport { width, height }.aspect_ratio());
+}
+
+/// Fills the `ActiveCamera` resource with every enabled camera, sorted by priority.
+/// Runs after `update_cameras`.
+#[system]
+#[read_component(Camera)]
+pub fn collect_cameras(
+	world: &SubWorld,
+	#[resource] active: &mut ActiveCamera,
+	#[resource] viewport: &Viewport,
+) {
+	let mut query = <(Entity, &Camera)>::query();
+	active.views = query
+		.iter(world)
+		.filter(|(_, camera)| camera.enabled)
+		.map(|(entity, camera)| CameraView::new(*entity, camera, viewport))
+		.filter(|view| view.output.width > 0 && view.output.height > 0)
+		.collect();
+	// Stable, so cameras with the same priority keep their order
+	active.views.sort_by_key(|view| view.priority);
+	active.view = None;
+}
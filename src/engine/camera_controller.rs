@@ -0,0 +1,128 @@
+use legion::*;
+use nalgebra::{Rotation3, Vector3};
+
+use crate::components::{
+	Camera, FlyCameraController, OrbitCameraController, Projection, Transform,
+};
+use crate::input::{apply_dead_zone, Actions, GamepadAxis, Input};
+use crate::util::radians;
+use crate::wrapper::window::Time;
+
+/// Rotation looking along `yaw` and `pitch` in radians, positive pitch looks down.
+fn look_rotation(yaw: f32, pitch: f32) -> Rotation3<f32> {
+	Rotation3::from_euler_angles(pitch, yaw, 0.0)
+}
+
+/// Degrees to turn this frame from the mouse and the right stick.
+/// The stick is read directly since the "look_x" and "look_y" axes
+/// also hold the mouse, which is scaled by `sensitivity` instead.
+fn look_delta(
+	input: &Input,
+	button: Option<glfw::MouseButton>,
+	sensitivity: f32,
+	look_speed: f32,
+	dead_zone: f32,
+	delta_time: f32,
+) -> (f32, f32) {
+	let stick = |axis| apply_dead_zone(input.gamepad_axis(axis), dead_zone);
+	let mut yaw = stick(GamepadAxis::RightX) * look_speed * delta_time;
+	let mut pitch = stick(GamepadAxis::RightY) * look_speed * delta_time;
+
+	let mouse_active = match button {
+		Some(button) => input.button_held(button),
+		None => true,
+	};
+	if mouse_active {
+		let delta = input.cursor_delta();
+		yaw += delta.x * sensitivity;
+		pitch += delta.y * sensitivity;
+	}
+
+	(yaw, pitch)
+}
+
+#[system(for_each)]
+pub fn fly_camera(
+	tf: &mut Transform,
+	controller: &FlyCameraController,
+	cam: &mut Camera,
+	#[resource] input: &Input,
+	#[resource] actions: &Actions,
+	#[resource] time: &Time,
+) {
+	let delta_time = time.delta_time;
+
+	// Angles are read back from the rotation, so other code may turn the camera too
+	let forward = tf.rotation * Vector3::z();
+	let yaw = forward.x.atan2(forward.z).to_degrees();
+	let pitch = (-forward.y).clamp(-1.0, 1.0).asin().to_degrees();
+
+	let (yaw_delta, pitch_delta) = look_delta(
+		input,
+		controller.look_button,
+		cam.mouse_sensitivity,
+		controller.look_speed,
+		controller.stick_dead_zone,
+		delta_time,
+	);
+	// Turning right moves forward towards -x
+	let yaw = yaw - yaw_delta;
+	let pitch = (pitch + pitch_delta).clamp(controller.min_pitch, controller.max_pitch);
+	tf.rotation = look_rotation(radians(yaw), radians(pitch));
+	tf.update_directions();
+
+	let mut speed = controller.speed;
+	if actions.held("sprint") {
+		speed *= controller.sprint_multiplier;
+	}
+	let movement = tf.forward * actions.axis("move_forward")
+		+ tf.right * actions.axis("move_right")
+		+ Vector3::y() * actions.axis("move_up");
+	tf.translate(movement * speed * delta_time);
+
+	let zoom = actions.axis("zoom");
+	if zoom != 0.0 {
+		match &mut cam.projection {
+			Projection::Perspective { fov, .. } | Projection::InfinitePerspective { fov, .. } => {
+				let degrees = (fov.degrees() - zoom * controller.zoom_speed)
+					.clamp(controller.min_fov, controller.max_fov);
+				*fov = fov.with_degrees(degrees);
+			}
+			Projection::Orthographic { height, .. } => {
+				*height = (*height * (1.0 - zoom * controller.ortho_zoom_speed)).max(0.01);
+			}
+		}
+		cam.update_projection();
+	}
+}
+
+#[system(for_each)]
+pub fn orbit_camera(
+	tf: &mut Transform,
+	controller: &mut OrbitCameraController,
+	cam: &Camera,
+	#[resource] input: &Input,
+	#[resource] actions: &Actions,
+	#[resource] time: &Time,
+) {
+	let (yaw_delta, pitch_delta) = look_delta(
+		input,
+		controller.rotate_button,
+		cam.mouse_sensitivity,
+		controller.look_speed,
+		controller.stick_dead_zone,
+		time.delta_time,
+	);
+	// The scene follows the drag
+	controller.yaw -= yaw_delta;
+	controller.pitch =
+		(controller.pitch + pitch_delta).clamp(controller.min_pitch, controller.max_pitch);
+
+	let zoom = actions.axis("zoom");
+	controller.distance = (controller.distance * (1.0 - zoom * controller.zoom_speed))
+		.clamp(controller.min_distance, controller.max_distance);
+
+	tf.rotation = look_rotation(radians(controller.yaw), radians(controller.pitch));
+	tf.update_directions();
+	tf.position = controller.target - tf.forward * controller.distance;
+}
@@ -0,0 +1,235 @@
+use legion::*;
+use nalgebra::Matrix4;
+
+use crate::components::{GlobalTransform, Renderable, Transform};
+use crate::engine::{
+	bind_environment, CameraBuffer, CameraView, Environment, LightBuffer, ResizeTargets,
+	ShadowRenderer,
+};
+use crate::wrapper::render::{
+	buffers::{FrameBuffer, RenderBuffer},
+	core::{shader::Shader, BlendMode, Texture, TextureOptions},
+	primitive::{Primitive, Quad},
+};
+
+/// First texture unit used by material maps in the forward pass.
+/// The G-buffer units of the lighting pass are free here,
+/// mesh textures still start at unit 0.
+const FORWARD_MAP_UNIT: u32 = 1;
+
+/// Draws blended materials after the lighting pass.
+/// Renderables are shaded with the same lights, shadows and environment
+/// as the deferred pass. `WeightedBlended` materials are resolved first
+/// without sorting, the others are sorted back to front and drawn on top.
+pub struct ForwardRenderer {
+	shader: Shader,
+
+	/// "accum" holds weighted color and revealage, "weight_sum" the weights
+	oit: FrameBuffer,
+	oit_shader: Shader,
+	composite_shader: Shader,
+
+	width: u32,
+	height: u32,
+}
+
+impl ForwardRenderer {
+	pub fn new(camera: &CameraBuffer, width: u32, height: u32) -> Result<ForwardRenderer, String> {
+		let shader = Shader::new("shaders/pbr/geometry.vs", "shaders/forward/forward.fs")?;
+		let oit_shader = Shader::new("shaders/pbr/geometry.vs", "shaders/forward/oit.fs")?;
+		let composite_shader =
+			Shader::new("shaders/post/quad.vs", "shaders/forward/oit_composite.fs")?;
+		camera.bind(&shader);
+		camera.bind(&oit_shader);
+
+		let mut oit = FrameBuffer::new();
+		let accum = Texture::for_framebuffer(
+			"accum",
+			0,
+			&TextureOptions {
+				width,
+				height,
+				internal_format: gl::RGBA16F,
+				format: gl::RGBA,
+				type_: gl::FLOAT,
+			},
+		);
+		let weight_sum = Texture::for_framebuffer(
+			"weight_sum",
+			1,
+			&TextureOptions {
+				width,
+				height,
+				internal_format: gl::R16F,
+				format: gl::RED,
+				type_: gl::FLOAT,
+			},
+		);
+		oit.add_texture(accum);
+		oit.add_texture(weight_sum);
+		oit.draw_buffers();
+		// Filled with the scene depth before each resolve
+		oit.set_depth_buffer(RenderBuffer::new(width, height));
+		oit.finish()?;
+
+		Ok(ForwardRenderer {
+			shader,
+			oit,
+			oit_shader,
+			composite_shader,
+			width,
+			height,
+		})
+	}
+
+	/// Blends transparent renderables into `target`, testing against its depth
+	/// buffer without writing to it. The depth of the G-buffer has to be
+	/// copied into `target` first, it has to be rendered from `camera`.
+	#[allow(clippy::too_many_arguments)]
+	pub fn render(
+		&self,
+		world: &World,
+		target: &FrameBuffer,
+		quad: &Quad,
+		camera: &CameraView,
+		lights: &LightBuffer,
+		shadows: &ShadowRenderer,
+		environment: Option<&Environment>,
+	) {
+		let mut sorted = Vec::new();
+		let mut unsorted = Vec::new();
+		let mut query = <(&Transform, Option<&GlobalTransform>, &Renderable)>::query();
+		for (tf, global, rend) in query.iter(world) {
+			if !rend.material.blend.is_blended() {
+				continue;
+			}
+			let model = match global {
+				Some(global) => global.matrix,
+				None => tf.get_matrix(),
+			};
+			match rend.material.blend {
+				BlendMode::WeightedBlended => unsorted.push((model, rend)),
+				_ => {
+					let position = model.column(3).xyz();
+					let distance = (position - camera.position).norm_squared();
+					sorted.push((distance, model, rend));
+				}
+			}
+		}
+		if sorted.is_empty() && unsorted.is_empty() {
+			return;
+		}
+
+		unsafe {
+			gl::Enable(gl::DEPTH_TEST);
+			gl::DepthFunc(camera.depth.func(gl::LESS));
+			gl::DepthMask(gl::FALSE);
+			gl::Enable(gl::BLEND);
+		}
+
+		for shader in [&self.shader, &self.oit_shader] {
+			shader.use_program();
+			lights.bind(shader);
+			shadows.bind(shader);
+			bind_environment(shader, environment);
+		}
+
+		if !unsorted.is_empty() {
+			self.render_weighted(&unsorted, target, quad);
+		}
+
+		// Farthest first so nearer surfaces blend over them
+		sorted.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+		target.bind();
+		self.shader.use_program();
+		for (_, model, rend) in sorted {
+			unsafe {
+				match rend.material.blend {
+					BlendMode::Additive => gl::BlendFunc(gl::SRC_ALPHA, gl::ONE),
+					_ => gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+				}
+			}
+			draw(&self.shader, &model, rend);
+		}
+
+		unsafe {
+			gl::Disable(gl::BLEND);
+			gl::DepthMask(gl::TRUE);
+		}
+	}
+
+	/// Accumulates weighted blended renderables and composites them over `target`.
+	/// Expects blending enabled and depth writes disabled.
+	fn render_weighted(
+		&self,
+		renderables: &[(Matrix4<f32>, &Renderable)],
+		target: &FrameBuffer,
+		quad: &Quad,
+	) {
+		let (width, height) = (self.width as i32, self.height as i32);
+		unsafe {
+			// Opaque depth, so hidden transparent surfaces are rejected
+			gl::BindFramebuffer(gl::READ_FRAMEBUFFER, target.fbo);
+			gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.oit.fbo);
+			gl::BlitFramebuffer(
+				0,
+				0,
+				width,
+				height,
+				0,
+				0,
+				width,
+				height,
+				gl::DEPTH_BUFFER_BIT,
+				gl::NEAREST,
+			);
+
+			self.oit.bind();
+			// Nothing accumulated, everything behind fully revealed
+			gl::ClearBufferfv(gl::COLOR, 0, [0.0f32, 0.0, 0.0, 1.0].as_ptr());
+			gl::ClearBufferfv(gl::COLOR, 1, [0.0f32; 4].as_ptr());
+			gl::BlendFuncSeparate(gl::ONE, gl::ONE, gl::ZERO, gl::ONE_MINUS_SRC_ALPHA);
+		}
+
+		self.oit_shader.use_program();
+		for (model, rend) in renderables {
+			draw(&self.oit_shader, model, rend);
+		}
+
+		target.bind();
+		unsafe {
+			gl::Disable(gl::DEPTH_TEST);
+			gl::BlendFunc(gl::ONE_MINUS_SRC_ALPHA, gl::SRC_ALPHA);
+		}
+
+		self.composite_shader.use_program();
+		self.composite_shader.set_int("accum", 0);
+		self.composite_shader.set_int("weight_sum", 1);
+		self.oit.get_buffer("accum").bind_unit(0);
+		self.oit.get_buffer("weight_sum").bind_unit(1);
+		quad.draw();
+
+		unsafe {
+			gl::Enable(gl::DEPTH_TEST);
+		}
+	}
+}
+
+impl ResizeTargets for ForwardRenderer {
+	fn resize_targets(&mut self, width: u32, height: u32) {
+		self.oit.resize(width, height);
+		self.width = width;
+		self.height = height;
+	}
+}
+
+fn draw(shader: &Shader, model: &Matrix4<f32>, rend: &Renderable) {
+	shader.set_mat4("model", model);
+	shader.set_mat4(
+		"normal_mat",
+		&model.try_inverse().expect("Could not inverse?").transpose(),
+	);
+	rend.material.use_with(shader, FORWARD_MAP_UNIT);
+	rend.mesh.draw(shader);
+}
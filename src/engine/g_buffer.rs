@@ -0,0 +1,49 @@
+use crate::engine::ResizeTargets;
+use crate::wrapper::render::{
+	buffers::{FrameBuffer, RenderBuffer},
+	core::{Texture, TextureOptions},
+};
+
+/// Render target of the geometry pass, read by SSAO and the lighting pass.
+/// Holds "position", "normal", "albedo" and "material" on attachments 0 to 3
+/// with a depth renderbuffer.
+pub struct GBuffer {
+	pub framebuffer: FrameBuffer,
+	pub width: u32,
+	pub height: u32,
+}
+
+impl GBuffer {
+	pub fn new(width: u32, height: u32) -> Result<GBuffer, String> {
+		let options = TextureOptions {
+			width,
+			height,
+			internal_format: gl::RGBA16F,
+			format: gl::RGBA,
+			type_: gl::FLOAT,
+		};
+
+		let mut framebuffer = FrameBuffer::new();
+		framebuffer.add_texture(Texture::for_framebuffer("position", 0, &options));
+		framebuffer.add_texture(Texture::for_framebuffer("normal", 1, &options));
+		framebuffer.add_texture(Texture::for_framebuffer("albedo", 2, &options));
+		framebuffer.add_texture(Texture::for_framebuffer("material", 3, &options));
+		framebuffer.draw_buffers();
+		framebuffer.set_depth_buffer(RenderBuffer::new(width, height));
+		framebuffer.finish()?;
+
+		Ok(GBuffer {
+			framebuffer,
+			width,
+			height,
+		})
+	}
+}
+
+impl ResizeTargets for GBuffer {
+	fn resize_targets(&mut self, width: u32, height: u32) {
+		self.framebuffer.resize(width, height);
+		self.width = width;
+		self.height = height;
+	}
+}
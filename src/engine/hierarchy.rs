@@ -0,0 +1,329 @@
+use legion::{
+	systems::{CommandBuffer, ParallelRunnable},
+	world::{EntityAccessError, SubWorld},
+	*,
+};
+use nalgebra::Matrix4;
+use std::collections::HashSet;
+
+use crate::components::{Children, GlobalTransform, Parent, Transform};
+
+/// Makes `child` move relative to `parent`.
+/// Any previous parent of `child` is replaced.
+pub fn set_parent(world: &mut World, child: Entity, parent: Entity) {
+	remove_parent(world, child);
+
+	if let Some(mut entry) = world.entry(parent) {
+		match entry.get_component_mut::<Children>() {
+			Ok(children) => children.0.push(child),
+			Err(_) => entry.add_component(Children(vec![child])),
+		}
+	}
+
+	if let Some(mut entry) = world.entry(child) {
+		entry.add_component(Parent(parent));
+	}
+}
+
+/// Detaches `child` from its parent, if it has one.
+pub fn remove_parent(world: &mut World, child: Entity) {
+	let parent = match world.entry(child) {
+		Some(mut entry) => match entry.get_component::<Parent>() {
+			Ok(parent) => {
+				let parent = parent.0;
+				entry.remove_component::<Parent>();
+				parent
+			}
+			Err(_) => return,
+		},
+		None => return,
+	};
+
+	if let Some(mut entry) = world.entry(parent) {
+		if let Ok(children) = entry.get_component_mut::<Children>() {
+			children.0.retain(|e| *e != child);
+		}
+	}
+}
+
+/// Propagates world matrices from changed entities down to their children.
+/// Only entities whose `Transform` or `Parent` may have changed since the last run
+/// are recomputed, together with everything below them.
+/// Entities with a `Transform` but no `GlobalTransform` get one added.
+/// A `Parent` that points at a deleted entity or closes a cycle is removed.
+pub fn propagate_transforms_system() -> impl ParallelRunnable {
+	SystemBuilder::new("propagate_transforms")
+		.read_component::<Transform>()
+		.read_component::<Parent>()
+		.read_component::<Children>()
+		.write_component::<GlobalTransform>()
+		.with_query(<(Entity, &Transform)>::query().filter(maybe_changed::<Transform>()))
+		.with_query(<(Entity, &Parent)>::query().filter(maybe_changed::<Parent>()))
+		.build(|commands, world, _, (moved, reparented)| {
+			let mut missing = <Entity>::query()
+				.filter(component::<Transform>() & !component::<GlobalTransform>());
+			for entity in missing.iter(world) {
+				commands.add_component(*entity, GlobalTransform::default());
+			}
+
+			// Deleting an entity leaves its children pointing at it
+			let mut parents = <(Entity, &Parent)>::query();
+			for (entity, parent) in parents.iter(world) {
+				if let Err(EntityAccessError::EntityNotFound) = world.entry_ref(parent.0) {
+					detach(commands, *entity);
+				}
+			}
+
+			let mut changed: HashSet<Entity> =
+				moved.iter(world).map(|(entity, _)| *entity).collect();
+			changed.extend(reparented.iter(world).map(|(entity, _)| *entity));
+
+			// Children of changed entities are updated with them
+			let mut tops = Vec::new();
+			let mut broken = HashSet::new();
+			for entity in &changed {
+				if let Some(parent) =
+					changed_ancestor(world, commands, &changed, &mut broken, *entity)
+				{
+					tops.push((*entity, parent));
+				}
+			}
+
+			let mut visited = HashSet::new();
+			for (entity, parent) in tops {
+				let parent = match parent {
+					Some(parent) => global_matrix(world, parent),
+					None => Matrix4::identity(),
+				};
+				update_global(world, &mut visited, entity, &parent);
+			}
+		})
+}
+
+/// Removes the `Parent` of `child` and its entry in the parent's `Children`.
+fn detach(commands: &mut CommandBuffer, child: Entity) {
+	commands.exec_mut(move |world, _| remove_parent(world, child));
+}
+
+fn parent_of(world: &SubWorld, entity: Entity) -> Option<Entity> {
+	let entry = world.entry_ref(entity).ok()?;
+	let parent = entry.get_component::<Parent>().ok()?;
+	Some(parent.0)
+}
+
+fn global_matrix(world: &SubWorld, entity: Entity) -> Matrix4<f32> {
+	match world.entry_ref(entity) {
+		Ok(entry) => match entry.get_component::<GlobalTransform>() {
+			Ok(global) => global.matrix,
+			Err(_) => Matrix4::identity(),
+		},
+		Err(_) => Matrix4::identity(),
+	}
+}
+
+/// Walks up from `entity` and returns its parent if no ancestor is in `changed`.
+/// Returns None if one is, the entity is then updated from there,
+/// and for entities in or below a cycle. Each cycle is broken by detaching one of its
+/// entities, preferring `entity` since its `Parent` likely closed the cycle.
+fn changed_ancestor(
+	world: &SubWorld,
+	commands: &mut CommandBuffer,
+	changed: &HashSet<Entity>,
+	broken: &mut HashSet<Entity>,
+	entity: Entity,
+) -> Option<Option<Entity>> {
+	let parent = parent_of(world, entity);
+	let mut path = vec![entity];
+	let mut below_changed = false;
+
+	let mut ancestor = parent;
+	while let Some(current) = ancestor {
+		if let Some(start) = path.iter().position(|e| *e == current) {
+			let cycle = &path[start..];
+			if !cycle.iter().any(|e| broken.contains(e)) {
+				detach(
+					commands,
+					if start == 0 {
+						entity
+					} else {
+						path[path.len() - 1]
+					},
+				);
+				broken.extend(cycle.iter().copied());
+			}
+			return None;
+		}
+
+		below_changed |= changed.contains(&current);
+		path.push(current);
+		ancestor = parent_of(world, current);
+	}
+
+	if below_changed {
+		None
+	} else {
+		Some(parent)
+	}
+}
+
+fn update_global(
+	world: &mut SubWorld,
+	visited: &mut HashSet<Entity>,
+	entity: Entity,
+	parent: &Matrix4<f32>,
+) {
+	if !visited.insert(entity) {
+		return;
+	}
+
+	let (local, children) = match world.entry_ref(entity) {
+		Ok(entry) => {
+			let local = match entry.get_component::<Transform>() {
+				Ok(tf) => tf.get_matrix(),
+				Err(_) => Matrix4::identity(),
+			};
+			let children = match entry.get_component::<Children>() {
+				Ok(children) => children.0.clone(),
+				Err(_) => Vec::new(),
+			};
+			(local, children)
+		}
+		Err(_) => return,
+	};
+
+	let matrix = match world.entry_mut(entity) {
+		Ok(mut entry) => match entry.get_component_mut::<GlobalTransform>() {
+			Ok(global) => {
+				global.matrix = parent * local;
+				global.matrix
+			}
+			Err(_) => return,
+		},
+		Err(_) => return,
+	};
+
+	for child in children {
+		update_global(world, visited, child, &matrix);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use nalgebra::{vector, Vector3};
+
+	fn at(position: Vector3<f32>) -> (Transform, GlobalTransform) {
+		let tf = Transform {
+			position,
+			..Transform::default()
+		};
+		(tf, GlobalTransform::default())
+	}
+
+	fn run(world: &mut World, schedule: &mut Schedule) {
+		schedule.execute(world, &mut Resources::default());
+	}
+
+	fn position(world: &World, entity: Entity) -> Vector3<f32> {
+		let entry = world.entry_ref(entity).unwrap();
+		entry.get_component::<GlobalTransform>().unwrap().position()
+	}
+
+	fn schedule() -> Schedule {
+		Schedule::builder()
+			.add_system(propagate_transforms_system())
+			.build()
+	}
+
+	#[test]
+	fn children_follow_parent() {
+		let mut world = World::default();
+		let mut schedule = schedule();
+		let parent = world.push(at(vector!(1.0, 0.0, 0.0)));
+		let child = world.push(at(vector!(0.0, 2.0, 0.0)));
+		set_parent(&mut world, child, parent);
+
+		run(&mut world, &mut schedule);
+		assert_eq!(position(&world, child), vector!(1.0, 2.0, 0.0));
+
+		let mut entry = world.entry(parent).unwrap();
+		entry.get_component_mut::<Transform>().unwrap().position = vector!(5.0, 0.0, 0.0);
+		run(&mut world, &mut schedule);
+		assert_eq!(position(&world, child), vector!(5.0, 2.0, 0.0));
+	}
+
+	#[test]
+	fn unchanged_entities_are_skipped() {
+		let mut world = World::default();
+		let mut schedule = schedule();
+		let entity = world.push(at(vector!(1.0, 0.0, 0.0)));
+		run(&mut world, &mut schedule);
+
+		// Nothing touched the transform, so the stale matrix survives
+		let mut entry = world.entry(entity).unwrap();
+		entry.get_component_mut::<GlobalTransform>().unwrap().matrix = Matrix4::zeros();
+		run(&mut world, &mut schedule);
+		assert_eq!(position(&world, entity), Vector3::zeros());
+	}
+
+	#[test]
+	fn adds_missing_global_transform() {
+		let mut world = World::default();
+		let mut schedule = schedule();
+		let entity = world.push((Transform {
+			position: vector!(0.0, 0.0, 3.0),
+			..Transform::default()
+		},));
+
+		run(&mut world, &mut schedule);
+		run(&mut world, &mut schedule);
+		assert_eq!(position(&world, entity), vector!(0.0, 0.0, 3.0));
+	}
+
+	#[test]
+	fn cycles_are_broken() {
+		let mut world = World::default();
+		let mut schedule = schedule();
+		let a = world.push(at(vector!(1.0, 0.0, 0.0)));
+		let b = world.push(at(vector!(0.0, 1.0, 0.0)));
+		run(&mut world, &mut schedule);
+
+		set_parent(&mut world, b, a);
+		set_parent(&mut world, a, b);
+		run(&mut world, &mut schedule);
+
+		let parented = [a, b]
+			.iter()
+			.filter(|e| {
+				world
+					.entry_ref(**e)
+					.unwrap()
+					.get_component::<Parent>()
+					.is_ok()
+			})
+			.count();
+		assert_eq!(parented, 1);
+
+		run(&mut world, &mut schedule);
+		let (a_pos, b_pos) = (position(&world, a), position(&world, b));
+		assert!(a_pos == vector!(1.0, 1.0, 0.0) || b_pos == vector!(1.0, 1.0, 0.0));
+	}
+
+	#[test]
+	fn deleted_parent_is_detached() {
+		let mut world = World::default();
+		let mut schedule = schedule();
+		let parent = world.push(at(vector!(1.0, 0.0, 0.0)));
+		let child = world.push(at(vector!(0.0, 2.0, 0.0)));
+		set_parent(&mut world, child, parent);
+		run(&mut world, &mut schedule);
+
+		world.remove(parent);
+		run(&mut world, &mut schedule);
+		let entry = world.entry_ref(child).unwrap();
+		assert!(entry.get_component::<Parent>().is_err());
+
+		run(&mut world, &mut schedule);
+		assert_eq!(position(&world, child), vector!(0.0, 2.0, 0.0));
+	}
+}
@@ -0,0 +1,529 @@
+use nalgebra::{Matrix4, Perspective3, Point3};
+use std::{
+	fs,
+	io::{self, Read, Write},
+	path::{Path, PathBuf},
+};
+
+use crate::util::cube_face_views;
+use crate::wrapper::{
+	error::EnvironmentError,
+	render::{
+		buffers::FrameBuffer,
+		core::{shader::Shader, Texture, TextureOptions},
+		primitive::{Cube, Primitive, Quad},
+	},
+};
+
+/// First texture unit used for the environment maps in the lighting pass.
+/// Units below this hold the G-buffer and shadow maps.
+pub const IBL_UNIT: u32 = 13;
+
+const ENVIRONMENT_SIZE: u32 = 512;
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTER_SIZE: u32 = 128;
+/// Roughness goes from 0 to 1 over the mip chain of the prefiltered map
+const PREFILTER_MIPS: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 512;
+
+const CACHE_MAGIC: &[u8; 4] = b"IBL1";
+
+/// Environment lighting precomputed from an equirectangular HDR image.
+pub struct Environment {
+	/// Cube map of the source image, mipmapped
+	pub environment: Texture,
+	/// Cosine weighted diffuse irradiance
+	pub irradiance: Texture,
+	/// GGX prefiltered radiance, one mip level per roughness step
+	pub prefiltered: Texture,
+	/// Scale and bias to F0 indexed by (N dot V, roughness)
+	pub brdf_lut: Texture,
+
+	pub intensity: f32,
+	/// Set when the maps were precomputed but could not be written to the cache,
+	/// the maps themselves are fine.
+	pub cache_error: Option<EnvironmentError>,
+}
+
+impl Environment {
+	/// Loads the environment maps for the HDR image at `path`.
+	/// With a `cache_dir` the precomputed maps are stored there and reused
+	/// until the image changes. `viewport` is restored afterwards.
+	/// A failed cache write is reported in `cache_error`.
+	pub fn load(
+		path: &str,
+		cache_dir: Option<&str>,
+		viewport: (u32, u32),
+	) -> Result<Environment, EnvironmentError> {
+		unsafe {
+			gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+		}
+
+		let cache = cache_dir.map(|dir| cache_path(dir, path));
+		if let Some(cache) = &cache {
+			if is_fresh(cache, path) {
+				// A broken cache file is regenerated below
+				if let Ok(environment) = Environment::read_cache(cache) {
+					return Ok(environment);
+				}
+			}
+		}
+
+		let mut environment = Environment::precompute(path, viewport)?;
+
+		if let Some(cache) = &cache {
+			if let Err(e) = environment.write_cache(cache) {
+				let cache = cache.display().to_string();
+				environment.cache_error = Some(EnvironmentError::Cache(cache, e));
+			}
+		}
+
+		Ok(environment)
+	}
+
+	fn precompute(path: &str, viewport: (u32, u32)) -> Result<Environment, EnvironmentError> {
+		let equirectangular = match Texture::from_hdr_file("equirectangular_map", path) {
+			Ok(texture) => texture,
+			Err(e) => return Err(EnvironmentError::Image(path.to_owned(), e)),
+		};
+
+		let to_cube = load_shader("shaders/ibl/cubemap.vs", "shaders/ibl/equirect.fs")?;
+		let irradiance_shader = load_shader("shaders/ibl/cubemap.vs", "shaders/ibl/irradiance.fs")?;
+		let prefilter_shader = load_shader("shaders/ibl/cubemap.vs", "shaders/ibl/prefilter.fs")?;
+		let brdf_shader = load_shader("shaders/ibl/brdf.vs", "shaders/ibl/brdf.fs")?;
+
+		let environment = Texture::cube("environment_map", &cube_options(ENVIRONMENT_SIZE), 10);
+		let irradiance = Texture::cube("irradiance_map", &cube_options(IRRADIANCE_SIZE), 1);
+		let prefiltered = Texture::cube(
+			"prefilter_map",
+			&cube_options(PREFILTER_SIZE),
+			PREFILTER_MIPS,
+		);
+
+		let capture = FrameBuffer::new();
+		let brdf_lut = Texture::clamped("brdf_lut", &brdf_options());
+		capture.attach_color_texture(&brdf_lut, 0);
+		if let Err(e) = capture.finish() {
+			return Err(EnvironmentError::Framebuffer(e.to_owned()));
+		}
+
+		let cube = Cube::new();
+		capture.bind();
+		unsafe {
+			gl::Disable(gl::DEPTH_TEST);
+		}
+
+		// Equirectangular image to cube map
+		to_cube.use_program();
+		to_cube.set_int("equirectangular_map", 0);
+		equirectangular.bind_unit(0);
+		render_faces(&capture, &to_cube, &cube, &environment, ENVIRONMENT_SIZE, 0);
+		environment.generate_mipmap();
+
+		// Diffuse irradiance
+		irradiance_shader.use_program();
+		irradiance_shader.set_int("environment_map", 0);
+		environment.bind_unit(0);
+		render_faces(
+			&capture,
+			&irradiance_shader,
+			&cube,
+			&irradiance,
+			IRRADIANCE_SIZE,
+			0,
+		);
+
+		// Specular, rougher surfaces read from smaller mips
+		prefilter_shader.use_program();
+		prefilter_shader.set_int("environment_map", 0);
+		prefilter_shader.set_float("resolution", ENVIRONMENT_SIZE as f32);
+		for mip in 0..PREFILTER_MIPS {
+			let roughness = mip as f32 / (PREFILTER_MIPS - 1) as f32;
+			prefilter_shader.set_float("roughness", roughness);
+			render_faces(
+				&capture,
+				&prefilter_shader,
+				&cube,
+				&prefiltered,
+				PREFILTER_SIZE >> mip,
+				mip,
+			);
+		}
+
+		// BRDF integration only depends on the view angle and roughness
+		brdf_shader.use_program();
+		capture.attach_color_texture(&brdf_lut, 0);
+		unsafe {
+			gl::Viewport(0, 0, BRDF_LUT_SIZE as i32, BRDF_LUT_SIZE as i32);
+			gl::Clear(gl::COLOR_BUFFER_BIT);
+		}
+		Quad::new().draw();
+
+		capture.unbind();
+		capture.delete();
+		equirectangular.delete();
+		unsafe {
+			gl::Enable(gl::DEPTH_TEST);
+			gl::Viewport(0, 0, viewport.0 as i32, viewport.1 as i32);
+		}
+
+		Ok(Environment {
+			environment,
+			irradiance,
+			prefiltered,
+			brdf_lut,
+			intensity: 1.0,
+			cache_error: None,
+		})
+	}
+
+	/// Binds the maps to the lighting shader.
+	pub fn bind(&self, shader: &Shader) {
+		set_units(shader);
+		shader.set_int("use_ibl", 1);
+		shader.set_float("environment_intensity", self.intensity);
+		shader.set_float("prefilter_max_lod", (PREFILTER_MIPS - 1) as f32);
+
+		self.irradiance.bind_unit(IBL_UNIT);
+		self.prefiltered.bind_unit(IBL_UNIT + 1);
+		self.brdf_lut.bind_unit(IBL_UNIT + 2);
+	}
+
+	/// Frees every map.
+	pub fn delete(&self) {
+		self.environment.delete();
+		self.irradiance.delete();
+		self.prefiltered.delete();
+		self.brdf_lut.delete();
+	}
+
+	/// Writes every map as raw little endian floats after a small header.
+	fn write_cache(&self, path: &Path) -> io::Result<()> {
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+
+		let mut data = Vec::new();
+		data.extend_from_slice(CACHE_MAGIC);
+		for value in cache_header() {
+			data.extend_from_slice(&value.to_le_bytes());
+		}
+
+		let mut write = |pixels: Vec<f32>| {
+			for value in pixels {
+				data.extend_from_slice(&value.to_le_bytes());
+			}
+		};
+
+		for face in 0..6 {
+			write(self.environment.read_image(cube_face(face), 0, gl::RGB, 3));
+		}
+		for face in 0..6 {
+			write(self.irradiance.read_image(cube_face(face), 0, gl::RGB, 3));
+		}
+		for mip in 0..PREFILTER_MIPS {
+			for face in 0..6 {
+				write(
+					self.prefiltered
+						.read_image(cube_face(face), mip, gl::RGB, 3),
+				);
+			}
+		}
+		write(self.brdf_lut.read_image(gl::TEXTURE_2D, 0, gl::RG, 2));
+
+		fs::File::create(path)?.write_all(&data)
+	}
+
+	fn read_cache(path: &Path) -> io::Result<Environment> {
+		// Read everything before creating textures so a short file leaks nothing
+		let maps = CacheMaps::read(path)?;
+
+		let environment = Texture::cube("environment_map", &cube_options(ENVIRONMENT_SIZE), 10);
+		let options = cube_options(ENVIRONMENT_SIZE);
+		for (face, pixels) in maps.environment.iter().enumerate() {
+			environment.set_image(cube_face(face as u32), 0, &options, pixels);
+		}
+		environment.generate_mipmap();
+
+		let irradiance = Texture::cube("irradiance_map", &cube_options(IRRADIANCE_SIZE), 1);
+		let options = cube_options(IRRADIANCE_SIZE);
+		for (face, pixels) in maps.irradiance.iter().enumerate() {
+			irradiance.set_image(cube_face(face as u32), 0, &options, pixels);
+		}
+
+		let prefiltered = Texture::cube(
+			"prefilter_map",
+			&cube_options(PREFILTER_SIZE),
+			PREFILTER_MIPS,
+		);
+		for (i, pixels) in maps.prefiltered.iter().enumerate() {
+			let (mip, face) = (i as u32 / 6, i as u32 % 6);
+			let options = cube_options(PREFILTER_SIZE >> mip);
+			prefiltered.set_image(cube_face(face), mip, &options, pixels);
+		}
+
+		let brdf_lut = Texture::clamped("brdf_lut", &brdf_options());
+		brdf_lut.set_image(gl::TEXTURE_2D, 0, &brdf_options(), &maps.brdf);
+
+		Ok(Environment {
+			environment,
+			irradiance,
+			prefiltered,
+			brdf_lut,
+			intensity: 1.0,
+			cache_error: None,
+		})
+	}
+}
+
+/// Pixels of every map in a cache file, faces in `cube_face` order
+/// and the prefiltered faces mip by mip.
+struct CacheMaps {
+	environment: Vec<Vec<f32>>,
+	irradiance: Vec<Vec<f32>>,
+	prefiltered: Vec<Vec<f32>>,
+	brdf: Vec<f32>,
+}
+
+impl CacheMaps {
+	fn read(path: &Path) -> io::Result<CacheMaps> {
+		let mut file = fs::File::open(path)?;
+
+		let mut magic = [0u8; 4];
+		file.read_exact(&mut magic)?;
+		if &magic != CACHE_MAGIC {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"not an environment cache",
+			));
+		}
+		for expected in cache_header() {
+			let mut value = [0u8; 4];
+			file.read_exact(&mut value)?;
+			if u32::from_le_bytes(value) != expected {
+				return Err(io::Error::new(
+					io::ErrorKind::InvalidData,
+					"cache was written with different sizes",
+				));
+			}
+		}
+
+		let mut read = |len: usize| -> io::Result<Vec<f32>> {
+			let mut bytes = vec![0u8; len * 4];
+			file.read_exact(&mut bytes)?;
+			Ok(bytes
+				.chunks_exact(4)
+				.map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+				.collect())
+		};
+
+		let face_len = |size: u32| (size * size * 3) as usize;
+		let mut environment = Vec::new();
+		for _ in 0..6 {
+			environment.push(read(face_len(ENVIRONMENT_SIZE))?);
+		}
+		let mut irradiance = Vec::new();
+		for _ in 0..6 {
+			irradiance.push(read(face_len(IRRADIANCE_SIZE))?);
+		}
+		let mut prefiltered = Vec::new();
+		for mip in 0..PREFILTER_MIPS {
+			for _ in 0..6 {
+				prefiltered.push(read(face_len(PREFILTER_SIZE >> mip))?);
+			}
+		}
+		let brdf = read((BRDF_LUT_SIZE * BRDF_LUT_SIZE * 2) as usize)?;
+
+		Ok(CacheMaps {
+			environment,
+			irradiance,
+			prefiltered,
+			brdf,
+		})
+	}
+}
+
+/// Binds the environment to the lighting shader, or disables image based
+/// lighting when there is none.
+pub fn bind_environment(shader: &Shader, environment: Option<&Environment>) {
+	match environment {
+		Some(environment) => environment.bind(shader),
+		None => {
+			// Samplers of different types may not share a unit, even when unused
+			set_units(shader);
+			shader.set_int("use_ibl", 0);
+		}
+	}
+}
+
+fn set_units(shader: &Shader) {
+	shader.set_int("irradiance_map", IBL_UNIT as i32);
+	shader.set_int("prefilter_map", (IBL_UNIT + 1) as i32);
+	shader.set_int("brdf_lut", (IBL_UNIT + 2) as i32);
+}
+
+/// Renders `cube` into all six faces of a mip level of `target`.
+/// The capture framebuffer and the shader must be bound.
+fn render_faces(
+	capture: &FrameBuffer,
+	shader: &Shader,
+	cube: &Cube,
+	target: &Texture,
+	size: u32,
+	mip: u32,
+) {
+	let projection: Matrix4<f32> =
+		Perspective3::new(1.0, std::f32::consts::FRAC_PI_2, 0.1, 10.0).to_homogeneous();
+	shader.set_mat4("projection", &projection);
+
+	unsafe {
+		gl::Viewport(0, 0, size as i32, size as i32);
+	}
+	for (face, view) in cube_face_views(&Point3::origin()).iter().enumerate() {
+		shader.set_mat4("view", view);
+		capture.attach_color_cube_face(target, face as u32, mip);
+		unsafe {
+			gl::Clear(gl::COLOR_BUFFER_BIT);
+		}
+		cube.draw();
+	}
+}
+
+fn load_shader(vertex: &str, fragment: &str) -> Result<Shader, EnvironmentError> {
+	match Shader::new(vertex, fragment) {
+		Ok(shader) => Ok(shader),
+		Err(e) => Err(EnvironmentError::Shader(e)),
+	}
+}
+
+fn cube_face(face: u32) -> gl::types::GLenum {
+	gl::TEXTURE_CUBE_MAP_POSITIVE_X + face
+}
+
+fn cube_options(size: u32) -> TextureOptions {
+	TextureOptions {
+		width: size,
+		height: size,
+		internal_format: gl::RGB16F,
+		format: gl::RGB,
+		type_: gl::FLOAT,
+	}
+}
+
+fn brdf_options() -> TextureOptions {
+	TextureOptions {
+		width: BRDF_LUT_SIZE,
+		height: BRDF_LUT_SIZE,
+		internal_format: gl::RG16F,
+		format: gl::RG,
+		type_: gl::FLOAT,
+	}
+}
+
+/// Sizes stored in the cache file, a cache with other sizes is ignored.
+fn cache_header() -> [u32; 5] {
+	[
+		ENVIRONMENT_SIZE,
+		IRRADIANCE_SIZE,
+		PREFILTER_SIZE,
+		PREFILTER_MIPS,
+		BRDF_LUT_SIZE,
+	]
+}
+
+/// Cache file for `image`, named after the file and a hash of its full path and
+/// the map sizes, so images with the same name or other settings don't share a file.
+/// The image itself doesn't have to exist, a cache can be shipped without it.
+fn cache_path(cache_dir: &str, image: &str) -> PathBuf {
+	let path = Path::new(image);
+	let name = match path.file_stem() {
+		Some(stem) => stem.to_string_lossy().into_owned(),
+		None => "environment".to_owned(),
+	};
+
+	// Only the directory is resolved, it outlives a deleted image
+	let dir = match path.parent() {
+		Some(dir) if dir != Path::new("") => dir,
+		_ => Path::new("."),
+	};
+	let full_path = match (fs::canonicalize(dir), path.file_name()) {
+		(Ok(dir), Some(file)) => dir.join(file).to_string_lossy().into_owned(),
+		_ => image.to_owned(),
+	};
+	let mut key = full_path.into_bytes();
+	for value in cache_header() {
+		key.extend_from_slice(&value.to_le_bytes());
+	}
+
+	Path::new(cache_dir).join(format!("{}-{:016x}.ibl", name, fnv1a(&key)))
+}
+
+/// 64 bit FNV-1a, stable across builds unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+	let mut hash: u64 = 0xcbf29ce484222325;
+	for byte in bytes {
+		hash ^= *byte as u64;
+		hash = hash.wrapping_mul(0x100000001b3);
+	}
+	hash
+}
+
+/// A cache is fresh when it is newer than the image it was made from.
+fn is_fresh(cache: &Path, image: &str) -> bool {
+	let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+	match (modified(cache), modified(Path::new(image))) {
+		(Some(cache), Some(image)) => cache >= image,
+		// The cache can be shipped without the source image
+		(Some(_), None) => true,
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cache_path_depends_on_full_path() {
+		let a = cache_path(".cache", "a/sky.hdr");
+		let b = cache_path(".cache", "b/sky.hdr");
+		assert_ne!(a, b);
+		assert_eq!(a, cache_path(".cache", "a/sky.hdr"));
+		assert!(a.file_name().unwrap().to_string_lossy().starts_with("sky-"));
+	}
+
+	#[test]
+	fn cache_loads_without_source_image() {
+		let dir = std::env::temp_dir().join(format!("ibl-cache-{}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		let image = dir.join("sky.hdr").to_string_lossy().into_owned();
+		let cache_dir = dir.join("cache").to_string_lossy().into_owned();
+		fs::write(&image, b"#?RADIANCE").unwrap();
+
+		// Same layout as `write_cache`, with black maps
+		let cache = cache_path(&cache_dir, &image);
+		let face = |size: u32| (size * size * 3) as usize;
+		let mut floats = 6 * face(ENVIRONMENT_SIZE) + 6 * face(IRRADIANCE_SIZE);
+		for mip in 0..PREFILTER_MIPS {
+			floats += 6 * face(PREFILTER_SIZE >> mip);
+		}
+		floats += (BRDF_LUT_SIZE * BRDF_LUT_SIZE * 2) as usize;
+		let mut data = CACHE_MAGIC.to_vec();
+		for value in cache_header() {
+			data.extend_from_slice(&value.to_le_bytes());
+		}
+		data.resize(data.len() + floats * 4, 0);
+		fs::create_dir_all(cache.parent().unwrap()).unwrap();
+		fs::write(&cache, data).unwrap();
+
+		fs::remove_file(&image).unwrap();
+		let shipped = cache_path(&cache_dir, &image);
+		assert_eq!(shipped, cache);
+		assert!(is_fresh(&shipped, &image));
+		let maps = CacheMaps::read(&shipped).unwrap();
+		assert_eq!(maps.prefiltered.len(), 6 * PREFILTER_MIPS as usize);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,144 @@
+use legion::{world::SubWorld, *};
+use nalgebra::Vector3;
+use std::mem::size_of;
+
+use crate::components::{GlobalTransform, Light, LightKind, Transform};
+use crate::engine::{ShadowMap, ShadowRenderer};
+use crate::wrapper::{
+	error::GLError,
+	render::{buffers::UniformBuffer, core::shader::Shader},
+};
+
+/// Must match MAX_LIGHTS in shaders/pbr/lighting.glsl
+pub const MAX_LIGHTS: usize = 64;
+
+/// One light in the `Lights` uniform block, std140 layout.
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct LightData {
+	/// xyz position, w range
+	pub position: [f32; 4],
+	/// xyz direction, w kind
+	pub direction: [f32; 4],
+	/// rgb color, a intensity
+	pub color: [f32; 4],
+	/// x cosine of inner angle, y cosine of outer angle
+	pub cone: [f32; 4],
+	/// x shadow map slot or -1, y depth bias, z texel size or far plane,
+	/// w 0 for shadow maps, 1 for cascades, 2 for cube maps
+	pub shadow: [f32; 4],
+	/// Column major light space matrix
+	pub light_space: [f32; 16],
+}
+
+impl LightData {
+	pub fn new(
+		light: &Light,
+		position: Vector3<f32>,
+		direction: Vector3<f32>,
+		shadow: Option<&ShadowMap>,
+	) -> LightData {
+		let (kind, cone) = match light.kind {
+			LightKind::Point => (0.0, [0.0; 4]),
+			LightKind::Spot {
+				inner_angle,
+				outer_angle,
+			} => (1.0, [inner_angle.cos(), outer_angle.cos(), 0.0, 0.0]),
+			LightKind::Directional => (2.0, [0.0; 4]),
+		};
+
+		let mut light_space = [0.0; 16];
+		let shadow = match shadow {
+			Some(map) => {
+				light_space.copy_from_slice(map.light_space.as_slice());
+				let texel = 1.0 / map.resolution as f32;
+				[map.slot as f32, map.bias, texel, 0.0]
+			}
+			None => [-1.0, 0.0, 0.0, 0.0],
+		};
+
+		LightData {
+			position: [position.x, position.y, position.z, light.range],
+			direction: [direction.x, direction.y, direction.z, kind],
+			color: [light.color.x, light.color.y, light.color.z, light.intensity],
+			cone,
+			shadow,
+			light_space,
+		}
+	}
+}
+
+/// Uniform buffer holding every light in the scene.
+pub struct LightBuffer {
+	ubo: UniformBuffer,
+	bind_point: u32,
+}
+
+impl LightBuffer {
+	pub fn new(bind_point: u32) -> Result<LightBuffer, GLError> {
+		let size = MAX_LIGHTS * size_of::<LightData>() + size_of::<[i32; 4]>();
+		let ubo = UniformBuffer::create_buffer(bind_point, size)?;
+
+		Ok(LightBuffer { ubo, bind_point })
+	}
+
+	/// Connects the `Lights` block of `shader` to this buffer.
+	pub fn bind(&self, shader: &Shader) {
+		UniformBuffer::set_uniform_block(shader, "Lights", self.bind_point);
+	}
+
+	/// Uploads lights, anything past `MAX_LIGHTS` is dropped.
+	pub fn upload(&self, lights: &[LightData]) {
+		let count = lights.len().min(MAX_LIGHTS);
+		self.ubo.set_data(0, &lights[..count]);
+		self.ubo
+			.set_data(MAX_LIGHTS * size_of::<LightData>(), &[count as i32]);
+	}
+}
+
+/// World position and forward direction of a light.
+pub fn light_orientation(
+	tf: &Transform,
+	global: Option<&GlobalTransform>,
+) -> (Vector3<f32>, Vector3<f32>) {
+	match global {
+		Some(global) => (global.position(), global.forward()),
+		None => (tf.position, tf.rotation * Vector3::z()),
+	}
+}
+
+/// Collects every light in the world and uploads them to the `LightBuffer` resource.
+#[system]
+#[read_component(Transform)]
+#[read_component(GlobalTransform)]
+#[read_component(Light)]
+pub fn upload_lights(
+	world: &SubWorld,
+	#[resource] buffer: &LightBuffer,
+	#[resource] shadows: &ShadowRenderer,
+) {
+	let mut lights = Vec::new();
+
+	let mut query = <(Entity, &Transform, Option<&GlobalTransform>, &Light)>::query();
+	for (entity, tf, global, light) in query.iter(world) {
+		let (position, direction) = light_orientation(tf, global);
+		let shadow = shadows.get(*entity);
+		let mut data = LightData::new(light, position, direction, shadow);
+
+		// Point lights store the far plane instead of a texel size
+		if let Some(map) = shadows.get_point(*entity) {
+			data.shadow = [map.slot as f32, map.bias, map.far, 2.0];
+		}
+
+		// Cascaded lights sample the cascade array instead of a shadow map slot
+		if let Some(map) = shadows.cascaded() {
+			if map.light == *entity {
+				let texel = 1.0 / map.resolution as f32;
+				data.shadow = [-1.0, map.bias, texel, 1.0];
+			}
+		}
+		lights.push(data);
+	}
+
+	buffer.upload(&lights);
+}
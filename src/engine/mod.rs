@@ -0,0 +1,37 @@
+mod app;
+mod assets;
+mod camera;
+mod camera_controller;
+mod forward;
+mod g_buffer;
+mod hierarchy;
+mod ibl;
+mod lighting;
+mod post_effects;
+mod post_process;
+mod render_targets;
+mod renderer;
+mod scene;
+mod shadow;
+mod sky;
+mod ssao;
+mod tonemap;
+
+pub use app::*;
+pub use assets::*;
+pub use camera::*;
+pub use camera_controller::*;
+pub use forward::*;
+pub use g_buffer::*;
+pub use hierarchy::*;
+pub use ibl::*;
+pub use lighting::*;
+pub use post_effects::*;
+pub use post_process::*;
+pub use render_targets::*;
+pub use renderer::*;
+pub use scene::*;
+pub use shadow::*;
+pub use sky::*;
+pub use ssao::*;
+pub use tonemap::*;
@@ -0,0 +1,428 @@
+use std::{any::Any, fs};
+
+use crate::engine::{color_target, PostContext, PostEffect};
+use crate::wrapper::{
+	error::{LutError, ShaderError},
+	render::{
+		buffers::FrameBuffer,
+		core::{shader::Shader, Texture},
+		primitive::Primitive,
+	},
+};
+
+/// Number of downsampled images bloom is blurred through
+const BLOOM_MIPS: u32 = 6;
+
+/// Bright parts of the image bleed into their surroundings.
+/// The image is blurred by downsampling and upsampling a mip chain.
+pub struct Bloom {
+	/// Amount of bloom mixed into the image
+	pub intensity: f32,
+	/// Luminance where bloom starts, with a soft knee below it
+	pub threshold: f32,
+	pub knee: f32,
+	/// Upsample filter radius in texture coordinates
+	pub radius: f32,
+
+	mips: Vec<(FrameBuffer, u32, u32)>,
+	downsample: Shader,
+	upsample: Shader,
+	composite: Shader,
+}
+
+impl Bloom {
+	pub fn new(width: u32, height: u32) -> Result<Bloom, String> {
+		let mut mips = Vec::new();
+		for mip in 1..=BLOOM_MIPS {
+			let (w, h) = ((width >> mip).max(1), (height >> mip).max(1));
+			mips.push((color_target(w, h, gl::RGBA16F)?, w, h));
+		}
+
+		Ok(Bloom {
+			intensity: 0.04,
+			threshold: 1.0,
+			knee: 0.5,
+			radius: 0.005,
+			mips,
+			downsample: Shader::new("shaders/post/quad.vs", "shaders/post/bloom_down.fs")?,
+			upsample: Shader::new("shaders/post/quad.vs", "shaders/post/bloom_up.fs")?,
+			composite: Shader::new("shaders/post/quad.vs", "shaders/post/bloom.fs")?,
+		})
+	}
+}
+
+impl PostEffect for Bloom {
+	fn apply(&mut self, input: &Texture, output: &FrameBuffer, context: &PostContext) {
+		// Downsample, thresholding into the first mip
+		self.downsample.use_program();
+		self.downsample.set_int("source", 0);
+		self.downsample.set_float("threshold", self.threshold);
+		self.downsample.set_float("knee", self.knee);
+
+		let mut source = input;
+		let mut source_size = (context.width, context.height);
+		for (i, (framebuffer, width, height)) in self.mips.iter().enumerate() {
+			framebuffer.bind();
+			unsafe {
+				gl::Viewport(0, 0, *width as i32, *height as i32);
+			}
+			self.downsample.set_int("prefilter", (i == 0) as i32);
+			self.downsample.set_vec2(
+				"source_texel",
+				1.0 / source_size.0 as f32,
+				1.0 / source_size.1 as f32,
+			);
+			source.bind_unit(0);
+			context.quad.draw();
+
+			source = framebuffer.get_buffer("color");
+			source_size = (*width, *height);
+		}
+
+		// Upsample, adding each level onto the larger one
+		self.upsample.use_program();
+		self.upsample.set_int("source", 0);
+		self.upsample.set_float("radius", self.radius);
+		unsafe {
+			gl::Enable(gl::BLEND);
+			gl::BlendFunc(gl::ONE, gl::ONE);
+		}
+		for i in (1..self.mips.len()).rev() {
+			let (target, width, height) = &self.mips[i - 1];
+			target.bind();
+			unsafe {
+				gl::Viewport(0, 0, *width as i32, *height as i32);
+			}
+			self.mips[i].0.get_buffer("color").bind_unit(0);
+			context.quad.draw();
+		}
+		unsafe {
+			gl::Disable(gl::BLEND);
+		}
+
+		output.bind();
+		unsafe {
+			gl::Viewport(0, 0, context.width as i32, context.height as i32);
+		}
+		self.composite.use_program();
+		self.composite.set_int("source", 0);
+		self.composite.set_int("bloom", 1);
+		self.composite.set_float("intensity", self.intensity);
+		input.bind_unit(0);
+		self.mips[0].0.get_buffer("color").bind_unit(1);
+		context.quad.draw();
+	}
+
+	fn resize(&mut self, width: u32, height: u32) {
+		for (mip, (framebuffer, w, h)) in self.mips.iter_mut().enumerate() {
+			// The first mip is half the size of the image
+			let shift = mip + 1;
+			*w = (width >> shift).max(1);
+			*h = (height >> shift).max(1);
+			framebuffer.resize(*w, *h);
+		}
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+/// Darkens the image towards its corners.
+pub struct Vignette {
+	pub intensity: f32,
+	/// Distance from the center where darkening starts, 1 is the corner
+	pub radius: f32,
+	/// Width of the transition
+	pub softness: f32,
+
+	shader: Shader,
+}
+
+impl Vignette {
+	pub fn new() -> Result<Vignette, ShaderError> {
+		Ok(Vignette {
+			intensity: 0.35,
+			radius: 0.75,
+			softness: 0.45,
+			shader: Shader::new("shaders/post/quad.vs", "shaders/post/vignette.fs")?,
+		})
+	}
+}
+
+impl PostEffect for Vignette {
+	fn apply(&mut self, input: &Texture, _output: &FrameBuffer, context: &PostContext) {
+		self.shader.use_program();
+		self.shader.set_int("source", 0);
+		self.shader.set_float("intensity", self.intensity);
+		self.shader.set_float("radius", self.radius);
+		self.shader.set_float("softness", self.softness);
+		input.bind_unit(0);
+		context.quad.draw();
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+/// Splits color channels apart towards the edges of the image.
+pub struct ChromaticAberration {
+	/// Offset of the red and blue channels at the corners, in texture coordinates
+	pub intensity: f32,
+
+	shader: Shader,
+}
+
+impl ChromaticAberration {
+	pub fn new() -> Result<ChromaticAberration, ShaderError> {
+		Ok(ChromaticAberration {
+			intensity: 0.004,
+			shader: Shader::new(
+				"shaders/post/quad.vs",
+				"shaders/post/chromatic_aberration.fs",
+			)?,
+		})
+	}
+}
+
+impl PostEffect for ChromaticAberration {
+	fn apply(&mut self, input: &Texture, _output: &FrameBuffer, context: &PostContext) {
+		self.shader.use_program();
+		self.shader.set_int("source", 0);
+		self.shader.set_float("intensity", self.intensity);
+		input.bind_unit(0);
+		context.quad.draw();
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+/// Animated noise over the image, strongest in the midtones.
+pub struct FilmGrain {
+	pub intensity: f32,
+
+	shader: Shader,
+}
+
+impl FilmGrain {
+	pub fn new() -> Result<FilmGrain, ShaderError> {
+		Ok(FilmGrain {
+			intensity: 0.04,
+			shader: Shader::new("shaders/post/quad.vs", "shaders/post/film_grain.fs")?,
+		})
+	}
+}
+
+impl PostEffect for FilmGrain {
+	fn apply(&mut self, input: &Texture, _output: &FrameBuffer, context: &PostContext) {
+		self.shader.use_program();
+		self.shader.set_int("source", 0);
+		self.shader.set_float("intensity", self.intensity);
+		self.shader.set_float("time", context.time);
+		input.bind_unit(0);
+		context.quad.draw();
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+/// Remaps colors through a 3D lookup table.
+/// Runs on display encoded color, so it belongs after tonemapping.
+pub struct ColorGrading {
+	/// Blend between the original and the graded color
+	pub intensity: f32,
+
+	lut: Texture,
+	shader: Shader,
+}
+
+impl ColorGrading {
+	/// Starts with a neutral LUT that leaves colors unchanged.
+	pub fn new() -> Result<ColorGrading, ShaderError> {
+		Ok(ColorGrading {
+			intensity: 1.0,
+			lut: neutral_lut(16),
+			shader: Shader::new("shaders/post/quad.vs", "shaders/post/color_grading.fs")?,
+		})
+	}
+
+	/// Replaces the LUT, freeing the previous one.
+	pub fn set_lut(&mut self, lut: Texture) {
+		self.lut.delete();
+		self.lut = lut;
+	}
+}
+
+impl PostEffect for ColorGrading {
+	fn apply(&mut self, input: &Texture, _output: &FrameBuffer, context: &PostContext) {
+		self.shader.use_program();
+		self.shader.set_int("source", 0);
+		self.shader.set_int("lut", 1);
+		self.shader.set_float("intensity", self.intensity);
+		input.bind_unit(0);
+		self.lut.bind_unit(1);
+		context.quad.draw();
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+/// LUT mapping every color to itself.
+pub fn neutral_lut(size: u32) -> Texture {
+	let step = 1.0 / (size - 1) as f32;
+	let mut data = Vec::with_capacity((size * size * size * 3) as usize);
+	for b in 0..size {
+		for g in 0..size {
+			for r in 0..size {
+				data.extend_from_slice(&[r as f32 * step, g as f32 * step, b as f32 * step]);
+			}
+		}
+	}
+
+	Texture::lut_3d("lut", size, &data)
+}
+
+/// Loads a 3D LUT in the Adobe .cube format.
+/// DOMAIN_MIN and DOMAIN_MAX are ignored, the domain is assumed to be 0 to 1.
+pub fn load_cube_lut(path: &str) -> Result<Texture, LutError> {
+	let src = match fs::read_to_string(path) {
+		Ok(src) => src,
+		Err(e) => return Err(LutError::Io(path.to_owned(), e)),
+	};
+	let (size, data) = parse_cube_lut(path, &src)?;
+
+	let mut texture = Texture::lut_3d("lut", size, &data);
+	texture.path = path.to_owned();
+
+	Ok(texture)
+}
+
+/// Reads the size and the RGB entries of a .cube file, red changing fastest.
+/// `path` is only used in errors.
+fn parse_cube_lut(path: &str, src: &str) -> Result<(u32, Vec<f32>), LutError> {
+	let error = |line: usize, message: &'static str| LutError::Parse {
+		path: path.to_owned(),
+		line: line + 1,
+		message,
+	};
+
+	let mut size = 0;
+	let mut data: Vec<f32> = Vec::new();
+	for (i, line) in src.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let mut words = line.split_whitespace();
+		let first = words.next().unwrap_or_default();
+		match first {
+			"LUT_3D_SIZE" => {
+				size = match words.next().map(|w| w.parse::<u32>()) {
+					Some(Ok(size)) if size >= 2 => size,
+					_ => return Err(error(i, "LUT_3D_SIZE must be a number of at least 2")),
+				};
+				data.reserve((size * size * size * 3) as usize);
+			}
+			"LUT_1D_SIZE" => return Err(error(i, "1D LUTs are not supported")),
+			"TITLE" | "DOMAIN_MIN" | "DOMAIN_MAX" => {}
+			_ => {
+				let row: Result<Vec<f32>, _> = line.split_whitespace().map(str::parse).collect();
+				match row {
+					Ok(row) if row.len() == 3 => data.extend_from_slice(&row),
+					_ => return Err(error(i, "expected three numbers")),
+				}
+			}
+		}
+	}
+
+	if size == 0 {
+		return Err(error(src.lines().count(), "missing LUT_3D_SIZE"));
+	}
+	if data.len() != (size * size * size * 3) as usize {
+		return Err(error(
+			src.lines().count(),
+			"number of entries does not match LUT_3D_SIZE",
+		));
+	}
+
+	Ok((size, data))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// 2x2x2 identity LUT with the optional header lines
+	const IDENTITY: &str = "# Created by hand
+TITLE \"identity\"
+LUT_3D_SIZE 2
+DOMAIN_MIN 0.0 0.0 0.0
+DOMAIN_MAX 1.0 1.0 1.0
+
+0.0 0.0 0.0
+1.0 0.0 0.0
+0.0 1.0 0.0
+1.0 1.0 0.0
+0.0 0.0 1.0
+1.0 0.0 1.0
+0.0 1.0 1.0
+1.0 1.0 1.0
+";
+
+	fn parse_error(src: &str) -> (usize, &'static str) {
+		match parse_cube_lut("test.cube", src) {
+			Err(LutError::Parse { line, message, .. }) => (line, message),
+			Err(e) => panic!("unexpected error {}", e),
+			Ok(_) => panic!("parsed an invalid LUT"),
+		}
+	}
+
+	#[test]
+	fn parses_identity_lut() {
+		let (size, data) = parse_cube_lut("test.cube", IDENTITY).unwrap();
+		assert_eq!(size, 2);
+		assert_eq!(data.len(), 2 * 2 * 2 * 3);
+		// Red changes fastest
+		assert_eq!(&data[3..6], &[1.0, 0.0, 0.0]);
+		assert_eq!(&data[21..24], &[1.0, 1.0, 1.0]);
+	}
+
+	#[test]
+	fn wrong_row_count() {
+		// Drops the last row
+		let src = IDENTITY.strip_suffix("1.0 1.0 1.0\n").unwrap();
+		assert_eq!(
+			parse_error(src),
+			(14, "number of entries does not match LUT_3D_SIZE")
+		);
+	}
+
+	#[test]
+	fn missing_size() {
+		let src = IDENTITY.replace("LUT_3D_SIZE 2\n", "");
+		assert_eq!(parse_error(&src), (14, "missing LUT_3D_SIZE"));
+
+		let src = IDENTITY.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 1");
+		assert_eq!(
+			parse_error(&src),
+			(3, "LUT_3D_SIZE must be a number of at least 2")
+		);
+	}
+
+	#[test]
+	fn malformed_number() {
+		let src = IDENTITY.replace("0.0 1.0 0.0", "0.0 one 0.0");
+		assert_eq!(parse_error(&src), (9, "expected three numbers"));
+
+		let src = IDENTITY.replace("0.0 1.0 0.0", "0.0 1.0");
+		assert_eq!(parse_error(&src), (9, "expected three numbers"));
+	}
+}
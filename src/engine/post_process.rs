@@ -0,0 +1,310 @@
+use std::any::Any;
+
+use crate::engine::{Bloom, ChromaticAberration, ColorGrading, FilmGrain, ResizeTargets, Vignette};
+use crate::wrapper::render::{
+	buffers::FrameBuffer,
+	core::{shader::Shader, Texture, TextureOptions, UniformValue},
+	primitive::{Primitive, Quad},
+};
+
+/// Frame data shared by every pass of a stack.
+pub struct PostContext<'a> {
+	pub quad: &'a Quad,
+	/// Seconds since start
+	pub time: f32,
+	/// Size of the stack's buffers
+	pub width: u32,
+	pub height: u32,
+}
+
+/// A full-screen effect in a `PostProcessStack`.
+pub trait PostEffect: Any {
+	/// Draws `input` with the effect applied into `output`.
+	/// `output` is bound with a viewport covering it when this is called,
+	/// effects that render elsewhere first must bind it again.
+	fn apply(&mut self, input: &Texture, output: &FrameBuffer, context: &PostContext);
+
+	/// Called when the stack is resized, effects with their own
+	/// size dependent buffers reallocate them here.
+	fn resize(&mut self, _width: u32, _height: u32) {}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// Effect made of a single shader and a list of uniforms.
+/// The shader reads the previous pass from `source` on texture unit 0.
+pub struct ShaderEffect {
+	pub shader: Shader,
+	pub uniforms: Vec<(String, UniformValue)>,
+}
+
+impl PostEffect for ShaderEffect {
+	fn apply(&mut self, input: &Texture, _output: &FrameBuffer, context: &PostContext) {
+		self.shader.use_program();
+		self.shader.set_int("source", 0);
+		self.shader.set_float("time", context.time);
+		input.bind_unit(0);
+
+		for (name, value) in &self.uniforms {
+			match value {
+				UniformValue::Int(v) => self.shader.set_int(name, *v),
+				UniformValue::Float(v) => self.shader.set_float(name, *v),
+				UniformValue::Vec3(v) => self.shader.set_vector3(name, v),
+			}
+		}
+
+		context.quad.draw();
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+pub struct PostPass {
+	pub name: String,
+	pub enabled: bool,
+	effect: Box<dyn PostEffect>,
+}
+
+/// Ordered list of full-screen passes, each reading the output of the one before.
+/// Passes ping-pong between two color buffers of the stack.
+pub struct PostProcessStack {
+	passes: Vec<PostPass>,
+	buffers: [FrameBuffer; 2],
+	copy: Shader,
+
+	width: u32,
+	height: u32,
+}
+
+impl PostProcessStack {
+	/// Creates an empty stack whose buffers use `internal_format`,
+	/// RGBA16F for HDR images and RGBA8 for display images.
+	pub fn new(
+		width: u32,
+		height: u32,
+		internal_format: gl::types::GLenum,
+	) -> Result<PostProcessStack, String> {
+		let copy = Shader::new("shaders/post/quad.vs", "shaders/post/copy.fs")?;
+		let buffers = [
+			color_target(width, height, internal_format)?,
+			color_target(width, height, internal_format)?,
+		];
+
+		Ok(PostProcessStack {
+			passes: Vec::new(),
+			buffers,
+			copy,
+			width,
+			height,
+		})
+	}
+
+	/// Adds an enabled pass after all others.
+	pub fn push(&mut self, name: &str, effect: impl PostEffect) {
+		self.insert(self.passes.len(), name, effect);
+	}
+
+	/// Adds an enabled pass at `index`, clamped to the end of the stack.
+	pub fn insert(&mut self, index: usize, name: &str, effect: impl PostEffect) {
+		let pass = PostPass {
+			name: name.to_owned(),
+			enabled: true,
+			effect: Box::new(effect),
+		};
+		self.passes.insert(index.min(self.passes.len()), pass);
+	}
+
+	pub fn remove(&mut self, name: &str) -> Option<Box<dyn PostEffect>> {
+		let index = self.index_of(name)?;
+		Some(self.passes.remove(index).effect)
+	}
+
+	/// Returns false if there is no pass called `name`.
+	pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+		match self.index_of(name) {
+			Some(index) => {
+				self.passes[index].enabled = enabled;
+				true
+			}
+			None => false,
+		}
+	}
+
+	pub fn is_enabled(&self, name: &str) -> bool {
+		match self.index_of(name) {
+			Some(index) => self.passes[index].enabled,
+			None => false,
+		}
+	}
+
+	/// Moves a pass to `index`, clamped to the end of the stack.
+	/// Returns false if there is no pass called `name`.
+	pub fn move_to(&mut self, name: &str, index: usize) -> bool {
+		match self.index_of(name) {
+			Some(current) => {
+				let pass = self.passes.remove(current);
+				self.passes.insert(index.min(self.passes.len()), pass);
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Passes in the order they run.
+	pub fn passes(&self) -> &[PostPass] {
+		&self.passes
+	}
+
+	/// Parameters of a pass, `T` must be the type the pass was added with.
+	pub fn get_mut<T: PostEffect>(&mut self, name: &str) -> Option<&mut T> {
+		let index = self.index_of(name)?;
+		self.passes[index].effect.as_any_mut().downcast_mut::<T>()
+	}
+
+	fn index_of(&self, name: &str) -> Option<usize> {
+		self.passes.iter().position(|pass| pass.name == name)
+	}
+
+	/// Binds the first buffer so an image can be rendered into the stack
+	/// and processed with `process`.
+	pub fn bind_input(&self) {
+		self.buffers[0].bind();
+	}
+
+	/// Runs the enabled passes over the image rendered after `bind_input`.
+	pub fn process(&mut self, context: &PostContext) -> Texture {
+		let input = self.buffers[0].get_buffer("color").clone();
+		self.run(input, 1, context)
+	}
+
+	/// Runs the enabled passes over `input`.
+	/// Returns `input` itself when no pass is enabled.
+	pub fn process_texture(&mut self, input: &Texture, context: &PostContext) -> Texture {
+		self.run(input.clone(), 0, context)
+	}
+
+	/// Leaves the default framebuffer bound.
+	fn run(&mut self, mut input: Texture, mut next: usize, context: &PostContext) -> Texture {
+		let context = PostContext {
+			width: self.width,
+			height: self.height,
+			..*context
+		};
+
+		unsafe {
+			gl::Disable(gl::DEPTH_TEST);
+		}
+
+		for pass in self.passes.iter_mut().filter(|pass| pass.enabled) {
+			let output = &self.buffers[next];
+			output.bind();
+			unsafe {
+				gl::Viewport(0, 0, self.width as i32, self.height as i32);
+			}
+
+			pass.effect.apply(&input, output, &context);
+
+			input = output.get_buffer("color").clone();
+			next = 1 - next;
+		}
+
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+			gl::Enable(gl::DEPTH_TEST);
+		}
+
+		input
+	}
+
+	/// Reallocates the buffers of the stack and of every pass, disabled ones included.
+	pub fn resize(&mut self, width: u32, height: u32) {
+		for buffer in &mut self.buffers {
+			buffer.resize(width, height);
+		}
+		for pass in &mut self.passes {
+			pass.effect.resize(width, height);
+		}
+		self.width = width;
+		self.height = height;
+	}
+
+	/// Copies `texture` into the bound framebuffer, usually the result of `process`.
+	pub fn present(&self, texture: &Texture, quad: &Quad) {
+		unsafe {
+			gl::Disable(gl::DEPTH_TEST);
+		}
+
+		self.copy.use_program();
+		self.copy.set_int("source", 0);
+		texture.bind_unit(0);
+		quad.draw();
+
+		unsafe {
+			gl::Enable(gl::DEPTH_TEST);
+		}
+	}
+}
+
+/// Framebuffer with a single linearly filtered color texture named "color".
+pub fn color_target(
+	width: u32,
+	height: u32,
+	internal_format: gl::types::GLenum,
+) -> Result<FrameBuffer, String> {
+	let format = match internal_format {
+		gl::RGBA8 => gl::UNSIGNED_BYTE,
+		_ => gl::FLOAT,
+	};
+
+	let mut framebuffer = FrameBuffer::new();
+	let texture = Texture::clamped(
+		"color",
+		&TextureOptions {
+			width,
+			height,
+			internal_format,
+			format: gl::RGBA,
+			type_: format,
+		},
+	);
+	framebuffer.attach_color_texture(&texture, 0);
+	framebuffer.add_texture(texture);
+	framebuffer.draw_buffers();
+	framebuffer.finish()?;
+
+	Ok(framebuffer)
+}
+
+/// Post processing before and after tonemapping.
+/// Effects that need light values above 1, like bloom, belong in `hdr`.
+pub struct PostProcessing {
+	pub hdr: PostProcessStack,
+	pub ldr: PostProcessStack,
+}
+
+impl PostProcessing {
+	/// Creates both stacks with the built-in effects.
+	/// Color grading starts disabled until a LUT is loaded.
+	pub fn new(width: u32, height: u32) -> Result<PostProcessing, String> {
+		let mut hdr = PostProcessStack::new(width, height, gl::RGBA16F)?;
+		hdr.push("bloom", Bloom::new(width, height)?);
+
+		let mut ldr = PostProcessStack::new(width, height, gl::RGBA8)?;
+		ldr.push("chromatic_aberration", ChromaticAberration::new()?);
+		ldr.push("color_grading", ColorGrading::new()?);
+		ldr.set_enabled("color_grading", false);
+		ldr.push("vignette", Vignette::new()?);
+		ldr.push("film_grain", FilmGrain::new()?);
+
+		Ok(PostProcessing { hdr, ldr })
+	}
+}
+
+impl ResizeTargets for PostProcessing {
+	fn resize_targets(&mut self, width: u32, height: u32) {
+		self.hdr.resize(width, height);
+		self.ldr.resize(width, height);
+	}
+}
@@ -0,0 +1,88 @@
+use legion::Resources;
+
+use crate::wrapper::window::Viewport;
+
+/// Size of a render target relative to the window's framebuffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizePolicy {
+	MatchWindow,
+	/// Fraction of the window size, 0.5 renders at half resolution
+	Scale(f32),
+	/// Same size whatever the window does
+	Fixed(u32, u32),
+}
+
+impl SizePolicy {
+	/// Target size for a window of this size, at least 1 by 1
+	pub fn size(&self, window: Viewport) -> (u32, u32) {
+		let (width, height) = match *self {
+			SizePolicy::MatchWindow => (window.width, window.height),
+			SizePolicy::Scale(scale) => (
+				(window.width as f32 * scale).round() as u32,
+				(window.height as f32 * scale).round() as u32,
+			),
+			SizePolicy::Fixed(width, height) => (width, height),
+		};
+
+		(width.max(1), height.max(1))
+	}
+}
+
+/// A resource owning framebuffers that have to follow the window size.
+pub trait ResizeTargets {
+	/// Reallocates every size dependent target, their contents are lost.
+	fn resize_targets(&mut self, width: u32, height: u32);
+}
+
+type ResizeFn = fn(&Resources, u32, u32);
+
+fn resize_resource<T: ResizeTargets + 'static>(resources: &Resources, width: u32, height: u32) {
+	if let Some(mut resource) = resources.get_mut::<T>() {
+		resource.resize_targets(width, height);
+	}
+}
+
+/// Resource listing the resources to resize when the window changes size.
+pub struct RenderTargets {
+	targets: Vec<(SizePolicy, ResizeFn)>,
+	/// Window size the targets were last sized for
+	window: Viewport,
+}
+
+impl RenderTargets {
+	/// `window` is the size the registered resources were created with.
+	pub fn new(window: Viewport) -> RenderTargets {
+		RenderTargets {
+			targets: Vec::new(),
+			window,
+		}
+	}
+
+	/// Resizes the resource `T` with `policy` from now on.
+	/// Resources missing when the window is resized are skipped.
+	pub fn register<T: ResizeTargets + 'static>(&mut self, policy: SizePolicy) {
+		self.targets.push((policy, resize_resource::<T>));
+	}
+
+	/// Resizes the registered resources if `window` differs from the last size.
+	/// A minimized window keeps the old targets.
+	pub fn resize(resources: &Resources, window: Viewport) {
+		let targets = {
+			let mut render_targets = match resources.get_mut::<RenderTargets>() {
+				Some(render_targets) => render_targets,
+				None => return,
+			};
+			if render_targets.window == window || window.width == 0 || window.height == 0 {
+				return;
+			}
+			render_targets.window = window;
+			// Resizing borrows other resources mutably, so this one is released first
+			render_targets.targets.clone()
+		};
+
+		for (policy, resize) in targets {
+			let (width, height) = policy.size(window);
+			resize(resources, width, height);
+		}
+	}
+}
@@ -0,0 +1,431 @@
+use legion::*;
+
+use crate::components::{GlobalTransform, Renderable, Transform};
+use crate::engine::{
+	bind_environment, collect_cameras_system, propagate_transforms_system, update_cameras_system,
+	upload_lights_system, ActiveCamera, App, CameraBuffer, Environment, ForwardRenderer, GBuffer,
+	LightBuffer, Plugin, PostContext, PostProcessing, RenderTargets, ShadowRenderer, SizePolicy,
+	Sky, SkyRenderer, Ssao, ToneMapping, TonemapSettings,
+};
+use crate::wrapper::{
+	render::{
+		core::shader::Shader,
+		primitive::{Primitive, Quad},
+	},
+	window::{Frame, Time, Viewport},
+};
+
+/// Bind points of the uniform blocks shared by every pass
+const CAMERA_BIND_POINT: u32 = 0;
+const LIGHTS_BIND_POINT: u32 = 1;
+
+#[system(for_each)]
+fn render_model(tf: &Transform, global: Option<&GlobalTransform>, rend: &Renderable) {
+	// Blended materials are drawn by the forward pass after lighting
+	if rend.material.blend.is_blended() {
+		return;
+	}
+
+	let mesh = &rend.mesh;
+	let shader = &rend.material.shader;
+	let model = match global {
+		Some(global) => global.matrix,
+		None => tf.get_matrix(),
+	};
+
+	shader.use_program();
+	shader.set_mat4("model", &model);
+	shader.set_mat4(
+		"normal_mat",
+		&model.try_inverse().expect("Could not inverse?").transpose(),
+	);
+
+	// Sets material properties to shader
+	rend.material.use_material();
+
+	mesh.draw(shader);
+}
+
+/// Shaders of the deferred pipeline, a resource.
+/// Opaque materials are drawn with `geometry_pass`.
+#[derive(Clone, Copy)]
+pub struct DeferredShaders {
+	pub geometry_pass: Shader,
+	pub light_pass: Shader,
+}
+
+/// Deferred pipeline, runs every pass once per camera.
+/// The internal render targets all have the size of the `GBuffer`,
+/// cameras stretch the image to their output.
+pub struct Renderer {
+	light_pass: Shader,
+	quad: Quad,
+	/// Draws the renderables into the G-buffer and uploads the lights, once per camera
+	schedule: Schedule,
+}
+
+impl Renderer {
+	/// Renders every enabled camera, from the lowest priority up.
+	pub fn render(&mut self, world: &mut World, resources: &mut Resources) {
+		// Areas no camera covers stay black
+		Frame::draw().clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+		let views = resources.get::<ActiveCamera>().unwrap().views().to_vec();
+		let viewport = {
+			let g_buffer = resources.get::<GBuffer>().unwrap();
+			(g_buffer.width, g_buffer.height)
+		};
+
+		// Shadow maps don't depend on the view, only the cascades are per camera
+		if let Err(e) = resources
+			.get_mut::<ShadowRenderer>()
+			.unwrap()
+			.render(world, viewport)
+		{
+			panic!("Shadow pass: {}", e);
+		}
+
+		let cameras: Vec<Entity> = views.iter().map(|view| view.entity).collect();
+		resources
+			.get_mut::<ToneMapping>()
+			.unwrap()
+			.retain_cameras(&cameras);
+
+		for view in views {
+			let buffer = resources.get::<CameraBuffer>().unwrap();
+			resources
+				.get_mut::<ActiveCamera>()
+				.unwrap()
+				.activate(view, &buffer);
+			drop(buffer);
+
+			self.render_camera(world, resources);
+		}
+
+		// Drawn once over the window, not into every camera
+		let ssao = resources.get::<Ssao>().unwrap();
+		if ssao.settings.debug {
+			let window = resources.get::<Viewport>().unwrap();
+			unsafe {
+				gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+				gl::Viewport(0, 0, window.width as i32, window.height as i32);
+			}
+			ssao.draw_debug(&self.quad);
+		}
+	}
+
+	/// Runs every pass for the active camera and presents the image to its output.
+	fn render_camera(&mut self, world: &mut World, resources: &mut Resources) {
+		let camera = match resources.get::<ActiveCamera>().unwrap().view() {
+			Some(view) => *view,
+			None => return,
+		};
+		let time = resources.get::<Time>().unwrap().clone();
+		let frame = Frame::draw();
+		let (light_pass, quad) = (&self.light_pass, &self.quad);
+		let (viewport, g_buffer_fbo) = {
+			let g_buffer = resources.get::<GBuffer>().unwrap();
+			((g_buffer.width, g_buffer.height), g_buffer.framebuffer.fbo)
+		};
+
+		// --------------
+		// 0. Shadow cascades, fitted to this camera
+		{
+			let active = resources.get::<ActiveCamera>().unwrap();
+			let mut shadows = resources.get_mut::<ShadowRenderer>().unwrap();
+			if let Err(e) = shadows.render_cascades(world, active.camera(world), viewport) {
+				panic!("Shadow pass: {}", e);
+			}
+		}
+
+		// --------------
+		// 1. Geometry pass
+
+		// Depth is reversed for cameras with an infinite far plane
+		let depth = camera.depth;
+		depth.apply();
+		unsafe {
+			gl::DepthFunc(depth.func(gl::LESS));
+		}
+
+		// Bind frame buffer for deferred rendering
+		// Binding framebuffer makes OpenGL render to buffer instead of window
+		resources.get::<GBuffer>().unwrap().framebuffer.bind();
+		unsafe {
+			gl::Viewport(0, 0, viewport.0 as i32, viewport.1 as i32);
+		}
+		frame.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+		self.schedule.execute(world, resources);
+
+		// --------------
+		// 1.5 Ambient occlusion, darkens the ao channel of g_material
+		{
+			let g_buffer = resources.get::<GBuffer>().unwrap();
+			let mut ssao = resources.get_mut::<Ssao>().unwrap();
+			ssao.render(
+				&g_buffer.framebuffer,
+				&camera.view,
+				&camera.projection,
+				quad,
+			);
+		}
+
+		// --------------
+		// 2. Lighting pass
+
+		unsafe {
+			gl::DepthFunc(depth.func(gl::LEQUAL));
+		}
+
+		// Lighting is rendered in linear HDR and tonemapped afterwards.
+		resources.get::<ToneMapping>().unwrap().bind_target();
+		frame.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+		// Use lighting shader
+		light_pass.use_program();
+		// Set framebuffer textures
+		resources
+			.get::<GBuffer>()
+			.unwrap()
+			.framebuffer
+			.activate_buffers();
+		resources.get::<ShadowRenderer>().unwrap().bind(light_pass);
+		bind_environment(light_pass, resources.get::<Environment>().as_deref());
+
+		// Every light is read from the Lights uniform block in one pass
+		quad.draw();
+
+		// --------------
+		// 2.5 copy content of geometry's depth buffer to the HDR target's depth buffer
+		let hdr_fbo = resources.get::<ToneMapping>().unwrap().target.fbo;
+		unsafe {
+			gl::BindFramebuffer(gl::READ_FRAMEBUFFER, g_buffer_fbo);
+			gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, hdr_fbo);
+
+			let (width, height) = (viewport.0 as i32, viewport.1 as i32);
+			gl::BlitFramebuffer(
+				0,
+				0,
+				width,
+				height,
+				0,
+				0,
+				width,
+				height,
+				gl::DEPTH_BUFFER_BIT,
+				gl::NEAREST,
+			);
+			gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+			gl::DepthFunc(depth.func(gl::LESS));
+		}
+
+		// --------------
+		// 2.6 Sky, fills the pixels no geometry was drawn to
+		resources.get::<ToneMapping>().unwrap().bind_target();
+		resources.get::<SkyRenderer>().unwrap().render(world, depth);
+
+		// --------------
+		// 2.75 Forward pass, blends transparent renderables over the lit image
+		{
+			let tone_mapping = resources.get::<ToneMapping>().unwrap();
+			resources.get::<ForwardRenderer>().unwrap().render(
+				world,
+				&tone_mapping.target,
+				quad,
+				&camera,
+				&resources.get::<LightBuffer>().unwrap(),
+				&resources.get::<ShadowRenderer>().unwrap(),
+				resources.get::<Environment>().as_deref(),
+			);
+		}
+
+		// --------------
+		// 3. Post processing around the tonemapping pass
+		{
+			let mut tone_mapping = resources.get_mut::<ToneMapping>().unwrap();
+			let mut post = resources.get_mut::<PostProcessing>().unwrap();
+			let context = PostContext {
+				quad,
+				time: time.last_frame as f32,
+				width: viewport.0,
+				height: viewport.1,
+			};
+
+			let hdr = post
+				.hdr
+				.process_texture(tone_mapping.hdr_texture(), &context);
+
+			post.ldr.bind_input();
+			tone_mapping.render(camera.entity, quad, &hdr, time.real_delta_time, viewport);
+			let ldr = post.ldr.process(&context);
+
+			// Covers the whole rectangle, so nothing needs clearing
+			camera.output.bind();
+			post.ldr.present(&ldr, quad);
+
+			unsafe {
+				gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+				gl::Viewport(0, 0, viewport.0 as i32, viewport.1 as i32);
+			}
+		}
+	}
+}
+
+/// Adds the deferred renderer with shadows, SSAO, sky, forward pass and
+/// post processing, and the systems keeping transforms and cameras up to date.
+/// Needs the `WindowPlugin`, and the `AssetsPlugin` before it for image based lighting.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderPlugin {
+	/// Size of the internal render targets
+	pub size_policy: SizePolicy,
+	pub tonemap: TonemapSettings,
+}
+
+impl Default for RenderPlugin {
+	fn default() -> RenderPlugin {
+		RenderPlugin {
+			size_policy: SizePolicy::MatchWindow,
+			tonemap: TonemapSettings::default(),
+		}
+	}
+}
+
+impl Plugin for RenderPlugin {
+	fn build(&self, app: &mut App) {
+		let window = app
+			.window
+			.as_ref()
+			.expect("RenderPlugin needs a window, add the WindowPlugin first");
+		let window_size = window.get_viewport();
+		let (width, height) = self.size_policy.size(window_size);
+		let resources = &mut app.resources;
+
+		let geometry_pass =
+			Shader::new("shaders/pbr/geometry.vs", "shaders/pbr/geometry.fs").unwrap();
+		let light_pass = Shader::new("shaders/pbr/light.vs", "shaders/pbr/light.fs").unwrap();
+		light_pass.use_program();
+		light_pass.set_int("g_position", 0);
+		light_pass.set_int("g_normal", 1);
+		light_pass.set_int("g_albedo", 2);
+		light_pass.set_int("g_material", 3);
+
+		let camera_buffer = match CameraBuffer::new(CAMERA_BIND_POINT) {
+			Ok(e) => e,
+			Err(e) => {
+				panic!("CameraBuffer: {}", e);
+			}
+		};
+		camera_buffer.bind(&geometry_pass);
+		camera_buffer.bind(&light_pass);
+
+		let light_buffer = match LightBuffer::new(LIGHTS_BIND_POINT) {
+			Ok(e) => e,
+			Err(e) => {
+				panic!("LightBuffer: {}", e);
+			}
+		};
+		light_buffer.bind(&light_pass);
+
+		let shadow_renderer = match ShadowRenderer::new() {
+			Ok(e) => e,
+			Err(e) => {
+				panic!("ShadowRenderer: {}", e);
+			}
+		};
+
+		// Background from the same image that lights the scene
+		let sky = match resources.get::<Environment>() {
+			Some(environment) => Sky::Cubemap {
+				texture: environment.environment.clone(),
+				intensity: 1.0,
+				lod: 0.0,
+			},
+			None => Sky::Atmosphere(Default::default()),
+		};
+		let sky_renderer = match SkyRenderer::new(&camera_buffer, sky) {
+			Ok(e) => e,
+			Err(e) => {
+				panic!("SkyRenderer: {}", e);
+			}
+		};
+
+		let mut tone_mapping = match ToneMapping::new(width, height) {
+			Ok(e) => e,
+			Err(e) => {
+				panic!("ToneMapping: {}", e);
+			}
+		};
+		tone_mapping.settings = self.tonemap;
+
+		let post_processing = match PostProcessing::new(width, height) {
+			Ok(e) => e,
+			Err(e) => {
+				panic!("PostProcessing: {}", e);
+			}
+		};
+
+		let forward_renderer = match ForwardRenderer::new(&camera_buffer, width, height) {
+			Ok(e) => e,
+			Err(e) => {
+				panic!("ForwardRenderer: {}", e);
+			}
+		};
+
+		let ssao = match Ssao::new(width, height) {
+			Ok(e) => e,
+			Err(e) => {
+				panic!("Ssao: {}", e);
+			}
+		};
+
+		let g_buffer = match GBuffer::new(width, height) {
+			Ok(e) => e,
+			Err(e) => {
+				panic!("GBuffer: {}", e);
+			}
+		};
+
+		let mut render_targets = RenderTargets::new(window_size);
+		render_targets.register::<GBuffer>(self.size_policy);
+		render_targets.register::<Ssao>(self.size_policy);
+		render_targets.register::<ToneMapping>(self.size_policy);
+		render_targets.register::<ForwardRenderer>(self.size_policy);
+		render_targets.register::<PostProcessing>(self.size_policy);
+
+		resources.insert(camera_buffer);
+		resources.insert(ActiveCamera::default());
+		resources.insert(light_buffer);
+		resources.insert(shadow_renderer);
+		resources.insert(sky_renderer);
+		resources.insert(tone_mapping);
+		resources.insert(post_processing);
+		resources.insert(forward_renderer);
+		resources.insert(ssao);
+		resources.insert(g_buffer);
+		resources.insert(render_targets);
+		resources.insert(DeferredShaders {
+			geometry_pass,
+			light_pass,
+		});
+
+		// Runs after gameplay, so every pass sees this frame's cameras
+		app.post_update
+			.add_system(propagate_transforms_system())
+			.flush()
+			.add_system(update_cameras_system())
+			.add_thread_local(collect_cameras_system());
+
+		let mut renderer = Renderer {
+			light_pass,
+			quad: Quad::new(),
+			schedule: Schedule::builder()
+				.add_thread_local(render_model_system())
+				.add_thread_local(upload_lights_system())
+				.build(),
+		};
+		app.render
+			.add_thread_local_fn(move |world, resources| renderer.render(world, resources));
+	}
+}
@@ -0,0 +1,48 @@
+use legion::{Entity, World};
+
+use crate::components::{GlobalTransform, Renderable, Transform};
+use crate::engine::set_parent;
+use crate::wrapper::render::core::{GltfNode, GltfScene};
+
+/// Spawns a glTF scene, keeping its node hierarchy.
+/// Every node becomes an entity and every primitive a child entity with a `Renderable`.
+/// Returns the root entities.
+pub fn spawn_scene(world: &mut World, scene: &GltfScene) -> Vec<Entity> {
+	let mut roots = Vec::new();
+	for node in &scene.nodes {
+		roots.push(spawn_node(world, node));
+	}
+
+	roots
+}
+
+fn spawn_node(world: &mut World, node: &GltfNode) -> Entity {
+	let entity = world.push((
+		Transform {
+			position: node.position,
+			rotation: node.rotation,
+			scale: node.scale,
+			..Transform::default()
+		},
+		GlobalTransform::default(),
+	));
+
+	for primitive in &node.primitives {
+		let child = world.push((
+			Transform::default(),
+			GlobalTransform::default(),
+			Renderable {
+				material: primitive.material.clone(),
+				mesh: primitive.mesh.clone(),
+			},
+		));
+		set_parent(world, child, entity);
+	}
+
+	for child in &node.children {
+		let child = spawn_node(world, child);
+		set_parent(world, child, entity);
+	}
+
+	entity
+}
@@ -0,0 +1,644 @@
+use legion::*;
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+use std::collections::HashMap;
+
+use crate::components::{
+	Camera, Cascades, GlobalTransform, Light, LightKind, Projection, Renderable, ShadowSettings,
+	Transform, MAX_CASCADES,
+};
+use crate::engine::light_orientation;
+use crate::wrapper::{
+	error::ShaderError,
+	render::{
+		buffers::FrameBuffer,
+		core::{shader::Shader, BlendMode, Texture, TextureOptions},
+	},
+	window::DepthRange,
+};
+
+/// Must match MAX_SHADOW_MAPS in shaders/pbr/lighting.glsl
+pub const MAX_SHADOW_MAPS: usize = 4;
+
+/// First texture unit used for shadow maps in the lighting pass.
+/// Units below this hold the G-buffer.
+pub const SHADOW_MAP_UNIT: u32 = 4;
+
+/// First texture unit of the material maps in the alpha tested depth pass,
+/// units below are left for mesh textures.
+const ALPHA_MAP_UNIT: u32 = 4;
+
+/// Depth map rendered from a single light.
+pub struct ShadowMap {
+	pub framebuffer: FrameBuffer,
+	pub texture: Texture,
+	pub resolution: u32,
+
+	/// Index into shadow_maps in the lighting shader.
+	pub slot: usize,
+	pub light_space: Matrix4<f32>,
+	pub bias: f32,
+}
+
+impl ShadowMap {
+	fn new(resolution: u32) -> Result<ShadowMap, String> {
+		let mut framebuffer = FrameBuffer::new();
+		let texture = Texture::depth_for_framebuffer(
+			"shadow_map",
+			&TextureOptions {
+				width: resolution,
+				height: resolution,
+				internal_format: gl::DEPTH_COMPONENT24,
+				format: gl::DEPTH_COMPONENT,
+				type_: gl::FLOAT,
+			},
+		);
+		framebuffer.set_depth_texture(texture.clone());
+		framebuffer.no_color_buffers();
+		framebuffer.finish()?;
+
+		Ok(ShadowMap {
+			framebuffer,
+			texture,
+			resolution,
+			slot: 0,
+			light_space: Matrix4::identity(),
+			bias: 0.0,
+		})
+	}
+}
+
+/// Texture unit of the cascade array in the lighting pass.
+pub const CASCADE_MAP_UNIT: u32 = SHADOW_MAP_UNIT + MAX_SHADOW_MAPS as u32;
+
+/// Must match MAX_POINT_SHADOW_MAPS in shaders/pbr/lighting.glsl
+pub const MAX_POINT_SHADOW_MAPS: usize = 4;
+
+/// First texture unit used for point light cube maps in the lighting pass.
+pub const POINT_SHADOW_MAP_UNIT: u32 = CASCADE_MAP_UNIT + 1;
+
+/// Cube map of distances to a point light.
+pub struct PointShadowMap {
+	pub framebuffer: FrameBuffer,
+	pub texture: Texture,
+	pub resolution: u32,
+
+	/// Index into point_shadow_maps in the lighting shader.
+	pub slot: usize,
+	pub far: f32,
+	pub bias: f32,
+}
+
+impl PointShadowMap {
+	fn new(resolution: u32) -> Result<PointShadowMap, String> {
+		let mut framebuffer = FrameBuffer::new();
+		let texture = Texture::depth_cube(
+			"point_shadow_map",
+			&TextureOptions {
+				width: resolution,
+				height: resolution,
+				internal_format: gl::DEPTH_COMPONENT24,
+				format: gl::DEPTH_COMPONENT,
+				type_: gl::FLOAT,
+			},
+		);
+		framebuffer.attach_depth_cube_face(&texture, 0);
+		framebuffer.set_depth_texture(texture.clone());
+		framebuffer.no_color_buffers();
+		framebuffer.finish()?;
+
+		Ok(PointShadowMap {
+			framebuffer,
+			texture,
+			resolution,
+			slot: 0,
+			far: 1.0,
+			bias: 0.0,
+		})
+	}
+}
+
+/// Cascaded depth maps of a directional light, one layer per cascade.
+pub struct CascadedShadowMap {
+	pub light: Entity,
+	pub framebuffer: FrameBuffer,
+	pub texture: Texture,
+	pub resolution: u32,
+
+	pub count: usize,
+	pub matrices: [Matrix4<f32>; MAX_CASCADES],
+	pub splits: [f32; MAX_CASCADES],
+	/// Camera view matrix the cascades were fitted to.
+	pub view: Matrix4<f32>,
+	pub bias: f32,
+}
+
+impl CascadedShadowMap {
+	fn new(light: Entity, resolution: u32) -> Result<CascadedShadowMap, String> {
+		let mut framebuffer = FrameBuffer::new();
+		let texture = Texture::depth_array(
+			"cascade_map",
+			&TextureOptions {
+				width: resolution,
+				height: resolution,
+				internal_format: gl::DEPTH_COMPONENT24,
+				format: gl::DEPTH_COMPONENT,
+				type_: gl::FLOAT,
+			},
+			MAX_CASCADES as u32,
+		);
+		framebuffer.attach_depth_layer(&texture, 0);
+		framebuffer.set_depth_texture(texture.clone());
+		framebuffer.no_color_buffers();
+		framebuffer.finish()?;
+
+		Ok(CascadedShadowMap {
+			light,
+			framebuffer,
+			texture,
+			resolution,
+			count: 0,
+			matrices: [Matrix4::identity(); MAX_CASCADES],
+			splits: [0.0; MAX_CASCADES],
+			view: Matrix4::identity(),
+			bias: 0.0,
+		})
+	}
+
+	/// Fits an orthographic projection around each slice of the camera frustum.
+	fn fit(&mut self, camera: &Camera, direction: Vector3<f32>, shadow: &ShadowSettings) {
+		let cascades: Cascades = match shadow.cascades {
+			Some(cascades) => cascades,
+			None => return,
+		};
+
+		let count = cascades.count.clamp(1, MAX_CASCADES);
+		let near = camera.near();
+		// Nothing past the last split is covered, which also gives infinite projections a far plane
+		let far = camera.far().min(cascades.splits[count - 1]);
+		let projection = camera.projection.with_far(far).matrix(camera.aspect_ratio);
+		let inverse = match (projection * camera.view).try_inverse() {
+			Some(inverse) => inverse,
+			None => return,
+		};
+
+		// Frustum corners on the near and far plane
+		let mut near_corners = [Vector3::zeros(); 4];
+		let mut far_corners = [Vector3::zeros(); 4];
+		let ndc = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+		for (i, (x, y)) in ndc.iter().enumerate() {
+			near_corners[i] = unproject(&inverse, *x, *y, -1.0);
+			far_corners[i] = unproject(&inverse, *x, *y, 1.0);
+		}
+
+		let mut start = near;
+		for i in 0..count {
+			let end = cascades.splits[i].clamp(start, far);
+			let t0 = (start - near) / (far - near);
+			let t1 = (end - near) / (far - near);
+
+			let mut corners = Vec::with_capacity(8);
+			for c in 0..4 {
+				let ray = far_corners[c] - near_corners[c];
+				corners.push(near_corners[c] + ray * t0);
+				corners.push(near_corners[c] + ray * t1);
+			}
+
+			self.matrices[i] = fit_cascade(&corners, direction, self.resolution, shadow.far);
+			self.splits[i] = end;
+			start = end;
+		}
+
+		self.count = count;
+		self.view = camera.view;
+		self.bias = shadow.bias;
+	}
+}
+
+fn unproject(inverse: &Matrix4<f32>, x: f32, y: f32, z: f32) -> Vector3<f32> {
+	let p = inverse * Vector4::new(x, y, z, 1.0);
+	p.xyz() / p.w
+}
+
+/// Orthographic light space matrix around a bounding sphere of `corners`.
+/// `depth` extends the volume towards the light to catch casters outside the slice.
+fn fit_cascade(
+	corners: &[Vector3<f32>],
+	direction: Vector3<f32>,
+	resolution: u32,
+	depth: f32,
+) -> Matrix4<f32> {
+	let mut center = Vector3::zeros();
+	for corner in corners {
+		center += corner;
+	}
+	center /= corners.len() as f32;
+
+	let mut radius: f32 = 0.0;
+	for corner in corners {
+		radius = radius.max((corner - center).norm());
+	}
+	// A fixed size keeps shadow edges from shimmering when the camera rotates
+	radius = (radius * 16.0).ceil() / 16.0;
+
+	let direction = direction.normalize();
+	let up = if direction.y.abs() > 0.99 {
+		Vector3::z()
+	} else {
+		Vector3::y()
+	};
+
+	// Snap center to whole texels in light space so edges don't crawl when moving
+	let rotation = Matrix4::look_at_rh(&Point3::origin(), &direction.into(), &up);
+	let texel = radius * 2.0 / resolution as f32;
+	let mut light_center = rotation.transform_point(&center.into());
+	light_center.x = (light_center.x / texel).floor() * texel;
+	light_center.y = (light_center.y / texel).floor() * texel;
+	let center = match rotation.try_inverse() {
+		Some(inverse) => inverse.transform_point(&light_center).coords,
+		None => center,
+	};
+
+	let eye: Point3<f32> = (center - direction * (radius + depth)).into();
+	let view = Matrix4::look_at_rh(&eye, &center.into(), &up);
+	let projection = Projection::Orthographic {
+		height: radius * 2.0,
+		near: 0.0,
+		far: radius * 2.0 + depth,
+	};
+
+	projection.matrix(1.0) * view
+}
+
+struct PointCaster {
+	entity: Entity,
+	shadow: ShadowSettings,
+	position: Vector3<f32>,
+	far: f32,
+	matrices: [Matrix4<f32>; 6],
+}
+
+/// Renders depth maps for every shadow casting light.
+/// The first directional light with cascades gets a cascaded shadow map,
+/// point lights get a cube map rendered in six passes.
+pub struct ShadowRenderer {
+	shader: DepthShader,
+	point_shader: DepthShader,
+	maps: HashMap<Entity, ShadowMap>,
+	point_maps: HashMap<Entity, PointShadowMap>,
+	cascaded: Option<CascadedShadowMap>,
+	/// Light picked for cascades by the last `render`, with its direction
+	cascade_caster: Option<(Entity, ShadowSettings, Vector3<f32>)>,
+
+	/// Tints the lit image by cascade to help tune split distances.
+	pub debug_cascades: bool,
+}
+
+impl ShadowRenderer {
+	pub fn new() -> Result<ShadowRenderer, ShaderError> {
+		let shader = DepthShader::new(
+			"shaders/shadow/depth.vs",
+			"shaders/shadow/depth.fs",
+			"shaders/shadow/depth_alpha.fs",
+		)?;
+		let point_shader = DepthShader::new(
+			"shaders/shadow/point.vs",
+			"shaders/shadow/point.fs",
+			"shaders/shadow/point_alpha.fs",
+		)?;
+
+		Ok(ShadowRenderer {
+			shader,
+			point_shader,
+			maps: HashMap::new(),
+			point_maps: HashMap::new(),
+			cascaded: None,
+			cascade_caster: None,
+			debug_cascades: false,
+		})
+	}
+
+	/// Shadow map of a light entity, if it was rendered this frame.
+	pub fn get(&self, light: Entity) -> Option<&ShadowMap> {
+		self.maps.get(&light)
+	}
+
+	/// Cube shadow map of a point light entity, if it was rendered this frame.
+	pub fn get_point(&self, light: Entity) -> Option<&PointShadowMap> {
+		self.point_maps.get(&light)
+	}
+
+	pub fn cascaded(&self) -> Option<&CascadedShadowMap> {
+		self.cascaded.as_ref()
+	}
+
+	/// Renders the shadow maps that don't depend on the camera, once per frame,
+	/// then restores the viewport to `viewport`.
+	/// Only the first `MAX_SHADOW_MAPS` casters get a shadow map.
+	/// The first directional light with cascades is left to `render_cascades`.
+	/// Fails if a new shadow map's framebuffer is incomplete.
+	pub fn render(&mut self, world: &World, viewport: (u32, u32)) -> Result<(), String> {
+		let mut casters: Vec<(Entity, ShadowSettings, Matrix4<f32>)> = Vec::new();
+		let mut cascaded: Option<(Entity, ShadowSettings, Vector3<f32>)> = None;
+		let mut point_casters: Vec<PointCaster> = Vec::new();
+		// Shadow maps always use standard depth, whatever the camera uses
+		DepthRange::Standard.apply();
+		unsafe {
+			gl::DepthFunc(gl::LESS);
+		}
+
+		let mut lights = <(Entity, &Transform, Option<&GlobalTransform>, &Light)>::query();
+		for (entity, tf, global, light) in lights.iter(world) {
+			let (position, direction) = light_orientation(tf, global);
+			let shadow = match light.shadow {
+				Some(shadow) => shadow,
+				None => continue,
+			};
+
+			let wants_cascades =
+				matches!(light.kind, LightKind::Directional) && shadow.cascades.is_some();
+			if wants_cascades && cascaded.is_none() {
+				cascaded = Some((*entity, shadow, direction));
+				continue;
+			}
+
+			if let LightKind::Point = light.kind {
+				if point_casters.len() == MAX_POINT_SHADOW_MAPS {
+					continue;
+				}
+				if let Some(matrices) = light.cube_matrices(position) {
+					point_casters.push(PointCaster {
+						entity: *entity,
+						shadow,
+						position,
+						far: shadow.far.min(light.range),
+						matrices,
+					});
+				}
+				continue;
+			}
+
+			if casters.len() == MAX_SHADOW_MAPS {
+				continue;
+			}
+			if let Some(matrix) = light.light_space_matrix(position, direction) {
+				casters.push((*entity, shadow, matrix));
+			}
+		}
+
+		self.cascade_caster = cascaded;
+		self.render_point_maps(world, point_casters)?;
+
+		// Free maps of lights that stopped casting shadows
+		self.maps.retain(|entity, map| {
+			let keep = casters.iter().any(|(e, _, _)| e == entity);
+			if !keep {
+				map.framebuffer.delete();
+			}
+			keep
+		});
+
+		for (slot, (entity, shadow, matrix)) in casters.into_iter().enumerate() {
+			let resolution = shadow.resolution.max(1);
+			let recreate = match self.maps.get(&entity) {
+				Some(map) => map.resolution != resolution,
+				None => true,
+			};
+			if recreate {
+				if let Some(old) = self.maps.insert(entity, ShadowMap::new(resolution)?) {
+					old.framebuffer.delete();
+				}
+			}
+
+			let map = self.maps.get_mut(&entity).unwrap();
+			map.slot = slot;
+			map.light_space = matrix;
+			map.bias = shadow.bias;
+
+			map.framebuffer.bind();
+			unsafe {
+				gl::Viewport(0, 0, resolution as i32, resolution as i32);
+				gl::Clear(gl::DEPTH_BUFFER_BIT);
+			}
+
+			self.shader
+				.set(|shader| shader.set_mat4("light_space", &matrix));
+			draw_renderables(world, &self.shader);
+		}
+
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+			gl::Viewport(0, 0, viewport.0 as i32, viewport.1 as i32);
+		}
+
+		Ok(())
+	}
+
+	fn render_point_maps(
+		&mut self,
+		world: &World,
+		casters: Vec<PointCaster>,
+	) -> Result<(), String> {
+		self.point_maps.retain(|entity, map| {
+			let keep = casters.iter().any(|c| c.entity == *entity);
+			if !keep {
+				map.framebuffer.delete();
+			}
+			keep
+		});
+
+		for (slot, caster) in casters.into_iter().enumerate() {
+			let entity = caster.entity;
+			let resolution = caster.shadow.resolution.max(1);
+			let recreate = match self.point_maps.get(&entity) {
+				Some(map) => map.resolution != resolution,
+				None => true,
+			};
+			if recreate {
+				if let Some(old) = self
+					.point_maps
+					.insert(entity, PointShadowMap::new(resolution)?)
+				{
+					old.framebuffer.delete();
+				}
+			}
+
+			let map = self.point_maps.get_mut(&entity).unwrap();
+			map.slot = slot;
+			map.far = caster.far;
+			map.bias = caster.shadow.bias;
+
+			map.framebuffer.bind();
+			unsafe {
+				gl::Viewport(0, 0, resolution as i32, resolution as i32);
+			}
+			self.point_shader.set(|shader| {
+				shader.set_vector3("light_pos", &caster.position);
+				shader.set_float("far_plane", caster.far);
+			});
+
+			for (face, matrix) in caster.matrices.iter().enumerate() {
+				map.framebuffer
+					.attach_depth_cube_face(&map.texture, face as u32);
+				unsafe {
+					gl::Clear(gl::DEPTH_BUFFER_BIT);
+				}
+
+				self.point_shader
+					.set(|shader| shader.set_mat4("light_space", matrix));
+				draw_renderables(world, &self.point_shader);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Fits the cascades to `camera` and renders them, once per camera after `render`.
+	/// Restores the viewport to `viewport`, cascades are dropped without a camera.
+	pub fn render_cascades(
+		&mut self,
+		world: &World,
+		camera: Option<&Camera>,
+		viewport: (u32, u32),
+	) -> Result<(), String> {
+		let (entity, shadow, direction, camera) = match (self.cascade_caster, camera) {
+			(Some((entity, shadow, direction)), Some(camera)) => {
+				(entity, shadow, direction, camera)
+			}
+			_ => {
+				if let Some(old) = self.cascaded.take() {
+					old.framebuffer.delete();
+				}
+				return Ok(());
+			}
+		};
+
+		let resolution = shadow.resolution.max(1);
+		let recreate = match &self.cascaded {
+			Some(map) => map.light != entity || map.resolution != resolution,
+			None => true,
+		};
+		if recreate {
+			if let Some(old) = self
+				.cascaded
+				.replace(CascadedShadowMap::new(entity, resolution)?)
+			{
+				old.framebuffer.delete();
+			}
+		}
+
+		let map = self.cascaded.as_mut().unwrap();
+		map.fit(camera, direction, &shadow);
+
+		DepthRange::Standard.apply();
+		unsafe {
+			gl::DepthFunc(gl::LESS);
+		}
+		map.framebuffer.bind();
+		unsafe {
+			gl::Viewport(0, 0, resolution as i32, resolution as i32);
+		}
+		for i in 0..map.count {
+			map.framebuffer.attach_depth_layer(&map.texture, i as u32);
+			unsafe {
+				gl::Clear(gl::DEPTH_BUFFER_BIT);
+			}
+
+			self.shader
+				.set(|shader| shader.set_mat4("light_space", &map.matrices[i]));
+			draw_renderables(world, &self.shader);
+		}
+
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+			gl::Viewport(0, 0, viewport.0 as i32, viewport.1 as i32);
+		}
+
+		Ok(())
+	}
+
+	/// Binds shadow maps for the lighting pass.
+	pub fn bind(&self, shader: &Shader) {
+		for slot in 0..MAX_SHADOW_MAPS {
+			let name = format!("shadow_maps[{}]", slot);
+			shader.set_int(&name, (SHADOW_MAP_UNIT + slot as u32) as i32);
+		}
+
+		for map in self.maps.values() {
+			map.texture.bind_unit(SHADOW_MAP_UNIT + map.slot as u32);
+		}
+
+		for slot in 0..MAX_POINT_SHADOW_MAPS {
+			let name = format!("point_shadow_maps[{}]", slot);
+			shader.set_int(&name, (POINT_SHADOW_MAP_UNIT + slot as u32) as i32);
+		}
+
+		for map in self.point_maps.values() {
+			map.texture
+				.bind_unit(POINT_SHADOW_MAP_UNIT + map.slot as u32);
+		}
+
+		shader.set_int("cascade_map", CASCADE_MAP_UNIT as i32);
+		shader.set_int("cascade_debug", self.debug_cascades as i32);
+		match &self.cascaded {
+			Some(map) => {
+				map.texture.bind_unit(CASCADE_MAP_UNIT);
+				shader.set_int("cascade_count", map.count as i32);
+				shader.set_mat4("cascade_view", &map.view);
+				for i in 0..map.count {
+					shader.set_mat4(&format!("cascade_matrices[{}]", i), &map.matrices[i]);
+					shader.set_float(&format!("cascade_splits[{}]", i), map.splits[i]);
+				}
+			}
+			None => shader.set_int("cascade_count", 0),
+		}
+	}
+}
+
+/// Depth shader with a variant for alpha tested materials,
+/// which discards the fragments the material cuts out.
+struct DepthShader {
+	opaque: Shader,
+	alpha_test: Shader,
+}
+
+impl DepthShader {
+	fn new(vertex: &str, fragment: &str, alpha_test: &str) -> Result<DepthShader, ShaderError> {
+		Ok(DepthShader {
+			opaque: Shader::new(vertex, fragment)?,
+			alpha_test: Shader::new(vertex, alpha_test)?,
+		})
+	}
+
+	/// Sets uniforms on both variants.
+	fn set(&self, set: impl Fn(&Shader)) {
+		for shader in [&self.opaque, &self.alpha_test] {
+			shader.use_program();
+			set(shader);
+		}
+	}
+}
+
+/// Draws every renderable that casts a shadow, blended materials do not.
+/// Alpha tested materials only cast a shadow where they aren't cut out.
+fn draw_renderables(world: &World, shaders: &DepthShader) {
+	let mut query = <(&Transform, Option<&GlobalTransform>, &Renderable)>::query();
+	for (tf, global, rend) in query.iter(world) {
+		let material = &rend.material;
+		let shader = match material.blend {
+			BlendMode::Opaque => &shaders.opaque,
+			BlendMode::AlphaTest(_) => &shaders.alpha_test,
+			BlendMode::AlphaBlend | BlendMode::Additive | BlendMode::WeightedBlended => continue,
+		};
+		shader.use_program();
+		if let BlendMode::AlphaTest(_) = material.blend {
+			material.use_with(shader, ALPHA_MAP_UNIT);
+		}
+
+		let model = match global {
+			Some(global) => global.matrix,
+			None => tf.get_matrix(),
+		};
+		shader.set_mat4("model", &model);
+		rend.mesh.draw(shader);
+	}
+}
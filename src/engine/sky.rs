@@ -0,0 +1,161 @@
+use legion::*;
+use nalgebra::{vector, Vector3};
+
+use crate::components::{GlobalTransform, Light, LightKind, Transform};
+use crate::engine::{light_orientation, CameraBuffer};
+use crate::wrapper::{
+	error::ShaderError,
+	render::{
+		core::{shader::Shader, Texture},
+		primitive::{Cube, Primitive},
+	},
+	window::DepthRange,
+};
+
+/// Parameters of the procedural sky, distances in meters.
+/// The defaults approximate the earth.
+#[derive(Clone, Copy, Debug)]
+pub struct Atmosphere {
+	pub sun_intensity: f32,
+	/// Angular radius of the sun disk in radians
+	pub sun_radius: f32,
+
+	pub planet_radius: f32,
+	pub atmosphere_radius: f32,
+	/// Height of the viewer above the ground
+	pub camera_height: f32,
+
+	/// Scattering coefficients at sea level per meter
+	pub rayleigh_scattering: Vector3<f32>,
+	pub mie_scattering: f32,
+	/// Heights where the density has fallen to 1/e
+	pub rayleigh_scale_height: f32,
+	pub mie_scale_height: f32,
+	/// Forward scattering of aerosols, between -1 and 1
+	pub mie_g: f32,
+}
+
+impl Default for Atmosphere {
+	fn default() -> Atmosphere {
+		Atmosphere {
+			sun_intensity: 22.0,
+			sun_radius: 0.0047,
+			planet_radius: 6_371e3,
+			atmosphere_radius: 6_471e3,
+			camera_height: 1.0,
+			rayleigh_scattering: vector![5.5e-6, 13.0e-6, 22.4e-6],
+			mie_scattering: 21e-6,
+			rayleigh_scale_height: 8e3,
+			mie_scale_height: 1.2e3,
+			mie_g: 0.758,
+		}
+	}
+}
+
+/// What `SkyRenderer` draws behind the scene.
+pub enum Sky {
+	/// Cube map, like `Environment::environment`
+	Cubemap {
+		texture: Texture,
+		intensity: f32,
+		/// Mip level sampled, higher levels blur the sky
+		lod: f32,
+	},
+	/// Sky lit by the first directional light in the world
+	Atmosphere(Atmosphere),
+}
+
+/// Draws the sky at the far plane, so only pixels not covered by geometry are filled.
+/// Runs after the lighting pass and the depth copy, into the HDR target.
+pub struct SkyRenderer {
+	pub sky: Sky,
+	/// Used by the atmosphere when the world has no directional light
+	pub default_sun: Vector3<f32>,
+
+	cube: Cube,
+	cubemap_shader: Shader,
+	atmosphere_shader: Shader,
+}
+
+impl SkyRenderer {
+	pub fn new(camera: &CameraBuffer, sky: Sky) -> Result<SkyRenderer, ShaderError> {
+		let cubemap_shader = Shader::new("shaders/sky/sky.vs", "shaders/sky/cubemap.fs")?;
+		let atmosphere_shader = Shader::new("shaders/sky/sky.vs", "shaders/sky/atmosphere.fs")?;
+		camera.bind(&cubemap_shader);
+		camera.bind(&atmosphere_shader);
+
+		Ok(SkyRenderer {
+			sky,
+			default_sun: Vector3::y(),
+			cube: Cube::new(),
+			cubemap_shader,
+			atmosphere_shader,
+		})
+	}
+
+	/// Draws the sky into the bound framebuffer, testing against its depth.
+	/// `depth` is the depth range the camera rendered the scene with.
+	pub fn render(&self, world: &World, depth: DepthRange) {
+		unsafe {
+			gl::Enable(gl::DEPTH_TEST);
+			gl::DepthFunc(depth.func(gl::LEQUAL));
+			gl::DepthMask(gl::FALSE);
+		}
+
+		let shader = match &self.sky {
+			Sky::Cubemap { .. } => &self.cubemap_shader,
+			Sky::Atmosphere(_) => &self.atmosphere_shader,
+		};
+		shader.use_program();
+		shader.set_float("far_ndc", depth.far_ndc());
+
+		match &self.sky {
+			Sky::Cubemap {
+				texture,
+				intensity,
+				lod,
+			} => {
+				let shader = &self.cubemap_shader;
+				shader.set_int("sky_map", 0);
+				shader.set_float("intensity", *intensity);
+				shader.set_float("lod", *lod);
+				texture.bind_unit(0);
+			}
+			Sky::Atmosphere(atmosphere) => {
+				let sun = sun_direction(world).unwrap_or(self.default_sun);
+				self.set_atmosphere(atmosphere, &sun);
+			}
+		}
+		self.cube.draw();
+
+		unsafe {
+			gl::DepthMask(gl::TRUE);
+			gl::DepthFunc(depth.func(gl::LESS));
+		}
+	}
+
+	/// Expects the atmosphere shader in use.
+	fn set_atmosphere(&self, atmosphere: &Atmosphere, sun: &Vector3<f32>) {
+		let shader = &self.atmosphere_shader;
+		shader.set_vector3("sun_direction", sun);
+		shader.set_float("sun_intensity", atmosphere.sun_intensity);
+		shader.set_float("sun_disk", atmosphere.sun_radius.cos());
+		shader.set_float("planet_radius", atmosphere.planet_radius);
+		shader.set_float("atmosphere_radius", atmosphere.atmosphere_radius);
+		shader.set_float("camera_height", atmosphere.camera_height);
+		shader.set_vector3("rayleigh_scattering", &atmosphere.rayleigh_scattering);
+		shader.set_float("rayleigh_scale_height", atmosphere.rayleigh_scale_height);
+		shader.set_float("mie_scattering", atmosphere.mie_scattering);
+		shader.set_float("mie_scale_height", atmosphere.mie_scale_height);
+		shader.set_float("mie_g", atmosphere.mie_g);
+	}
+}
+
+/// Direction towards the first directional light, the light shines the other way.
+pub fn sun_direction(world: &World) -> Option<Vector3<f32>> {
+	let mut query = <(&Transform, Option<&GlobalTransform>, &Light)>::query();
+	query
+		.iter(world)
+		.find(|(_, _, light)| matches!(light.kind, LightKind::Directional))
+		.map(|(tf, global, _)| -light_orientation(tf, global).1.normalize())
+}
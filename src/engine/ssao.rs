@@ -0,0 +1,225 @@
+use nalgebra::{Matrix4, Vector3};
+use rand::Rng;
+
+use crate::engine::{color_target, ResizeTargets};
+use crate::wrapper::render::{
+	buffers::FrameBuffer,
+	core::{shader::Shader, Texture, TextureOptions},
+	primitive::{Primitive, Quad},
+};
+
+/// Must match MAX_SAMPLES in shaders/ssao/ssao.fs
+pub const MAX_SSAO_SAMPLES: usize = 64;
+
+/// Side length of the tiled rotation noise, also the blur size
+const NOISE_SIZE: u32 = 4;
+
+/// Attachment of the G-buffer that holds metallic, roughness and ao.
+const MATERIAL_ATTACHMENT: u32 = 3;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SsaoSettings {
+	pub enabled: bool,
+	/// Radius of the sampled hemisphere in world units
+	pub radius: f32,
+	/// Depth difference ignored to avoid self occlusion
+	pub bias: f32,
+	/// Number of kernel samples, at most `MAX_SSAO_SAMPLES`
+	pub samples: usize,
+	/// Draws the raw ao buffer over the screen with `Ssao::draw_debug`
+	pub debug: bool,
+}
+
+impl Default for SsaoSettings {
+	fn default() -> SsaoSettings {
+		SsaoSettings {
+			enabled: true,
+			radius: 0.5,
+			bias: 0.025,
+			samples: 32,
+			debug: false,
+		}
+	}
+}
+
+/// Screen space ambient occlusion from the G-buffer.
+/// The blurred result is multiplied into the ao channel of g_material,
+/// so the lighting pass applies it to ambient light only.
+pub struct Ssao {
+	pub settings: SsaoSettings,
+
+	kernel: Vec<Vector3<f32>>,
+	noise: Texture,
+
+	/// Raw, unblurred occlusion
+	framebuffer: FrameBuffer,
+	shader: Shader,
+	blur_shader: Shader,
+	debug_shader: Shader,
+
+	width: u32,
+	height: u32,
+}
+
+impl Ssao {
+	pub fn new(width: u32, height: u32) -> Result<Ssao, String> {
+		let mut rng = rand::thread_rng();
+
+		// Rotations around the z axis of the kernel
+		let mut noise_data = Vec::new();
+		for _ in 0..NOISE_SIZE * NOISE_SIZE {
+			noise_data.extend_from_slice(&[
+				rng.gen_range(-1.0..1.0),
+				rng.gen_range(-1.0..1.0),
+				0.0,
+			]);
+		}
+		let options = TextureOptions {
+			width: NOISE_SIZE,
+			height: NOISE_SIZE,
+			internal_format: gl::RGB16F,
+			format: gl::RGB,
+			type_: gl::FLOAT,
+		};
+		let noise = Texture::clamped("noise", &options);
+		noise.set_image(gl::TEXTURE_2D, 0, &options, &noise_data);
+		noise.set_parameters(gl::NEAREST, gl::REPEAT);
+
+		let settings = SsaoSettings::default();
+
+		Ok(Ssao {
+			kernel: create_kernel(settings.samples),
+			settings,
+			noise,
+			framebuffer: color_target(width, height, gl::R16F)?,
+			shader: Shader::new("shaders/post/quad.vs", "shaders/ssao/ssao.fs")?,
+			blur_shader: Shader::new("shaders/post/quad.vs", "shaders/ssao/blur.fs")?,
+			debug_shader: Shader::new("shaders/post/quad.vs", "shaders/ssao/debug.fs")?,
+			width,
+			height,
+		})
+	}
+
+	/// Raw occlusion of the last `render`, 1 is unoccluded.
+	pub fn ao_texture(&self) -> &Texture {
+		self.framebuffer.get_buffer("color")
+	}
+
+	/// Computes occlusion for the filled G-buffer and darkens its ao channel.
+	/// Leaves the G-buffer bound.
+	pub fn render(
+		&mut self,
+		g_buffer: &FrameBuffer,
+		view: &Matrix4<f32>,
+		projection: &Matrix4<f32>,
+		quad: &Quad,
+	) {
+		if !self.settings.enabled {
+			return;
+		}
+
+		let samples = self.settings.samples.clamp(1, MAX_SSAO_SAMPLES);
+		if samples != self.kernel.len() {
+			self.kernel = create_kernel(samples);
+		}
+
+		unsafe {
+			gl::Disable(gl::DEPTH_TEST);
+			gl::Viewport(0, 0, self.width as i32, self.height as i32);
+		}
+
+		self.framebuffer.bind();
+		self.shader.use_program();
+		self.shader.set_int("g_position", 0);
+		self.shader.set_int("g_normal", 1);
+		self.shader.set_int("noise", 2);
+		self.shader.set_int("sample_count", samples as i32);
+		self.shader.set_float("radius", self.settings.radius);
+		self.shader.set_float("bias", self.settings.bias);
+		self.shader.set_mat4("view", view);
+		self.shader.set_mat4("projection", projection);
+		self.shader.set_vec2(
+			"noise_scale",
+			self.width as f32 / NOISE_SIZE as f32,
+			self.height as f32 / NOISE_SIZE as f32,
+		);
+		for (i, sample) in self.kernel.iter().enumerate() {
+			self.shader.set_vector3(&format!("samples[{}]", i), sample);
+		}
+		g_buffer.get_buffer("position").bind_unit(0);
+		g_buffer.get_buffer("normal").bind_unit(1);
+		self.noise.bind_unit(2);
+		quad.draw();
+
+		// Blur straight into g_material, multiplying only its ao channel
+		g_buffer.bind();
+		unsafe {
+			gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+			gl::ColorMaski(
+				MATERIAL_ATTACHMENT,
+				gl::FALSE,
+				gl::FALSE,
+				gl::TRUE,
+				gl::FALSE,
+			);
+			gl::Enable(gl::BLEND);
+			gl::BlendFunc(gl::DST_COLOR, gl::ZERO);
+		}
+
+		self.blur_shader.use_program();
+		self.blur_shader.set_int("ao", 0);
+		self.ao_texture().bind_unit(0);
+		quad.draw();
+
+		unsafe {
+			gl::Disable(gl::BLEND);
+			gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+			gl::Enable(gl::DEPTH_TEST);
+		}
+	}
+
+	/// Draws the raw ao buffer into the bound framebuffer.
+	pub fn draw_debug(&self, quad: &Quad) {
+		unsafe {
+			gl::Disable(gl::DEPTH_TEST);
+		}
+
+		self.debug_shader.use_program();
+		self.debug_shader.set_int("ao", 0);
+		self.ao_texture().bind_unit(0);
+		quad.draw();
+
+		unsafe {
+			gl::Enable(gl::DEPTH_TEST);
+		}
+	}
+}
+
+impl ResizeTargets for Ssao {
+	fn resize_targets(&mut self, width: u32, height: u32) {
+		self.framebuffer.resize(width, height);
+		self.width = width;
+		self.height = height;
+	}
+}
+
+/// Points in the unit hemisphere around +z, denser close to the origin.
+fn create_kernel(samples: usize) -> Vec<Vector3<f32>> {
+	let mut rng = rand::thread_rng();
+
+	let mut kernel = Vec::with_capacity(samples);
+	for i in 0..samples {
+		let sample = Vector3::new(
+			rng.gen_range(-1.0..1.0),
+			rng.gen_range(-1.0..1.0),
+			rng.gen_range(0.0..1.0),
+		);
+		let sample = sample.normalize() * rng.gen_range(0.0..1.0);
+
+		let t = i as f32 / samples as f32;
+		let scale = 0.1 + 0.9 * t * t;
+		kernel.push(sample * scale);
+	}
+
+	kernel
+}
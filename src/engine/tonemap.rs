@@ -0,0 +1,344 @@
+use legion::Entity;
+use std::collections::HashMap;
+
+use crate::engine::ResizeTargets;
+use crate::wrapper::render::{
+	buffers::{FrameBuffer, PixelBuffer, RenderBuffer},
+	core::{shader::Shader, Texture, TextureOptions},
+	primitive::{Primitive, Quad},
+};
+
+/// Size of the log luminance image read back for automatic exposure.
+/// Must match output_size in shaders/post/luminance.fs
+const LUMINANCE_SIZE: u32 = 64;
+pub const HISTOGRAM_BINS: usize = 64;
+
+/// Curve mapping scene referred HDR color to the display range.
+/// Values must match the defines in shaders/post/tonemap.fs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TonemapOperator {
+	Reinhard = 0,
+	Aces = 1,
+	Uncharted2 = 2,
+	AgX = 3,
+}
+
+/// Adapts exposure to the average scene luminance.
+/// The average skips the darkest and brightest parts of the luminance histogram.
+#[derive(Clone, Copy, Debug)]
+pub struct AutoExposure {
+	/// Histogram range in log2 luminance, values outside are clamped
+	pub min_log_luminance: f32,
+	pub max_log_luminance: f32,
+	/// Fractions of pixels ignored at the dark and bright ends, from 0 to 1.
+	/// Together they must stay below 1, 0.5 drops the darker half.
+	pub low_fraction: f32,
+	pub high_fraction: f32,
+	/// Adaptation rates per second towards brighter and darker scenes
+	pub speed_up: f32,
+	pub speed_down: f32,
+	/// Added to the adapted exposure, in stops
+	pub compensation: f32,
+}
+
+impl Default for AutoExposure {
+	fn default() -> AutoExposure {
+		AutoExposure {
+			min_log_luminance: -10.0,
+			max_log_luminance: 6.0,
+			low_fraction: 0.5,
+			high_fraction: 0.05,
+			speed_up: 3.0,
+			speed_down: 1.0,
+			compensation: 0.0,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Exposure {
+	/// Exposure in stops, 0 leaves the image unchanged
+	Manual(f32),
+	Auto(AutoExposure),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TonemapSettings {
+	pub operator: TonemapOperator,
+	pub exposure: Exposure,
+}
+
+impl Default for TonemapSettings {
+	fn default() -> TonemapSettings {
+		TonemapSettings {
+			operator: TonemapOperator::Aces,
+			exposure: Exposure::Manual(0.0),
+		}
+	}
+}
+
+/// Exposure state of one camera, cameras adapt independently.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraExposure {
+	/// Exposure used last frame, in stops
+	pub exposure: f32,
+	/// Luminance histogram of the last frame, empty with manual exposure
+	pub histogram: [u32; HISTOGRAM_BINS],
+}
+
+impl Default for CameraExposure {
+	fn default() -> CameraExposure {
+		CameraExposure {
+			exposure: 0.0,
+			histogram: [0; HISTOGRAM_BINS],
+		}
+	}
+}
+
+/// HDR render target and the pass that tonemaps it to the bound framebuffer.
+/// `settings` can be changed at any time and apply on the next `render`.
+pub struct ToneMapping {
+	pub settings: TonemapSettings,
+
+	/// RGBA16F color named "hdr" with a depth renderbuffer
+	pub target: FrameBuffer,
+	shader: Shader,
+
+	luminance: FrameBuffer,
+	luminance_shader: Shader,
+
+	cameras: HashMap<Entity, CameraExposure>,
+	/// Luminance of each camera read back a frame late
+	readbacks: HashMap<Entity, PixelBuffer>,
+}
+
+impl ToneMapping {
+	pub fn new(width: u32, height: u32) -> Result<ToneMapping, String> {
+		let shader = Shader::new("shaders/post/quad.vs", "shaders/post/tonemap.fs")?;
+		let luminance_shader = Shader::new("shaders/post/quad.vs", "shaders/post/luminance.fs")?;
+
+		let mut target = FrameBuffer::new();
+		let hdr = Texture::for_framebuffer(
+			"hdr",
+			0,
+			&TextureOptions {
+				width,
+				height,
+				internal_format: gl::RGBA16F,
+				format: gl::RGBA,
+				type_: gl::FLOAT,
+			},
+		);
+		target.add_texture(hdr);
+		target.draw_buffers();
+		target.set_depth_buffer(RenderBuffer::new(width, height));
+		target.finish()?;
+
+		let mut luminance = FrameBuffer::new();
+		let texture = Texture::for_framebuffer(
+			"luminance",
+			0,
+			&TextureOptions {
+				width: LUMINANCE_SIZE,
+				height: LUMINANCE_SIZE,
+				internal_format: gl::R16F,
+				format: gl::RED,
+				type_: gl::FLOAT,
+			},
+		);
+		luminance.add_texture(texture);
+		luminance.draw_buffers();
+		luminance.finish()?;
+
+		Ok(ToneMapping {
+			settings: TonemapSettings::default(),
+			target,
+			shader,
+			luminance,
+			luminance_shader,
+			cameras: HashMap::new(),
+			readbacks: HashMap::new(),
+		})
+	}
+
+	/// Binds the HDR target, the lighting pass renders into it.
+	pub fn bind_target(&self) {
+		self.target.bind();
+	}
+
+	pub fn hdr_texture(&self) -> &Texture {
+		self.target.get_buffer("hdr")
+	}
+
+	/// Exposure of `camera` applied by the last `render`, None before its first.
+	pub fn exposure(&self, camera: Entity) -> Option<&CameraExposure> {
+		self.cameras.get(&camera)
+	}
+
+	/// Forgets the exposure of cameras not in `cameras`, like deleted ones.
+	pub fn retain_cameras(&mut self, cameras: &[Entity]) {
+		self.cameras.retain(|entity, _| cameras.contains(entity));
+		self.readbacks.retain(|entity, readback| {
+			let keep = cameras.contains(entity);
+			if !keep {
+				readback.delete();
+			}
+			keep
+		});
+	}
+
+	/// Tonemaps `input` into the currently bound framebuffer.
+	/// `input` is the HDR target or a processed copy of it, rendered by `camera`,
+	/// `viewport` is the size of the bound framebuffer.
+	pub fn render(
+		&mut self,
+		camera: Entity,
+		quad: &Quad,
+		input: &Texture,
+		delta_time: f32,
+		viewport: (u32, u32),
+	) {
+		let mut state = self.cameras.get(&camera).copied().unwrap_or_default();
+		match self.settings.exposure {
+			Exposure::Manual(exposure) => {
+				state = CameraExposure {
+					exposure,
+					..Default::default()
+				};
+				if let Some(readback) = self.readbacks.remove(&camera) {
+					readback.delete();
+				}
+			}
+			Exposure::Auto(auto) => {
+				let mut readback = self.readbacks.remove(&camera).unwrap_or_else(|| {
+					PixelBuffer::new((LUMINANCE_SIZE * LUMINANCE_SIZE) as usize * 4)
+				});
+				self.adapt(
+					&auto,
+					&mut state,
+					&mut readback,
+					quad,
+					input,
+					delta_time,
+					viewport,
+				);
+				self.readbacks.insert(camera, readback);
+			}
+		}
+		self.cameras.insert(camera, state);
+
+		unsafe {
+			gl::Disable(gl::DEPTH_TEST);
+		}
+
+		self.shader.use_program();
+		self.shader.set_int("hdr_buffer", 0);
+		self.shader.set_float("exposure", state.exposure.exp2());
+		self.shader
+			.set_int("operator", self.settings.operator as i32);
+		input.bind_unit(0);
+		quad.draw();
+
+		unsafe {
+			gl::Enable(gl::DEPTH_TEST);
+		}
+	}
+
+	/// Measures the scene luminance and moves the exposure towards it.
+	/// The luminance is read back through `readback` a frame late,
+	/// so the CPU never waits for the GPU to finish the frame.
+	#[allow(clippy::too_many_arguments)]
+	fn adapt(
+		&self,
+		auto: &AutoExposure,
+		state: &mut CameraExposure,
+		readback: &mut PixelBuffer,
+		quad: &Quad,
+		input: &Texture,
+		delta_time: f32,
+		viewport: (u32, u32),
+	) {
+		let mut previous = 0;
+		unsafe {
+			gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous);
+			gl::Viewport(0, 0, LUMINANCE_SIZE as i32, LUMINANCE_SIZE as i32);
+			gl::Disable(gl::DEPTH_TEST);
+		}
+
+		self.luminance.bind();
+		self.luminance_shader.use_program();
+		self.luminance_shader.set_int("hdr_buffer", 0);
+		input.bind_unit(0);
+		quad.draw();
+
+		// Last frame's copy has finished by now, the new one is taken next frame
+		let log_luminance = readback.take_f32();
+		readback.read_pixels(LUMINANCE_SIZE, LUMINANCE_SIZE, gl::RED, gl::FLOAT);
+
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, previous as u32);
+			gl::Viewport(0, 0, viewport.0 as i32, viewport.1 as i32);
+			gl::Enable(gl::DEPTH_TEST);
+		}
+
+		let log_luminance = match log_luminance {
+			Some(log_luminance) => log_luminance,
+			None => return,
+		};
+
+		let range = auto.max_log_luminance - auto.min_log_luminance;
+		state.histogram = [0; HISTOGRAM_BINS];
+		for value in &log_luminance {
+			let t = (value - auto.min_log_luminance) / range;
+			let bin = (t * HISTOGRAM_BINS as f32) as i32;
+			state.histogram[bin.clamp(0, HISTOGRAM_BINS as i32 - 1) as usize] += 1;
+		}
+
+		let average = histogram_average(&state.histogram, auto);
+
+		// Expose the average luminance as middle gray
+		let target = (0.18f32).log2() - average + auto.compensation;
+		// A brighter scene lowers the exposure
+		let speed = match target < state.exposure {
+			true => auto.speed_up,
+			false => auto.speed_down,
+		};
+		state.exposure += (target - state.exposure) * (1.0 - (-delta_time * speed).exp());
+	}
+}
+
+impl ResizeTargets for ToneMapping {
+	/// The luminance image has a fixed size and is kept
+	fn resize_targets(&mut self, width: u32, height: u32) {
+		self.target.resize(width, height);
+	}
+}
+
+/// Average log2 luminance of the histogram after dropping the outer percentiles.
+fn histogram_average(histogram: &[u32; HISTOGRAM_BINS], auto: &AutoExposure) -> f32 {
+	let total: u32 = histogram.iter().sum();
+	let low = total as f32 * auto.low_fraction.clamp(0.0, 1.0);
+	let high = total as f32 * (1.0 - auto.high_fraction.clamp(0.0, 1.0));
+	let range = auto.max_log_luminance - auto.min_log_luminance;
+
+	let mut seen = 0.0;
+	let mut sum = 0.0;
+	let mut count = 0.0;
+	for (bin, amount) in histogram.iter().enumerate() {
+		let start = seen;
+		seen += *amount as f32;
+
+		// Part of this bin that lies between the low and high cut
+		let used = seen.min(high) - start.max(low);
+		if used > 0.0 {
+			let center = (bin as f32 + 0.5) / HISTOGRAM_BINS as f32;
+			sum += (auto.min_log_luminance + center * range) * used;
+			count += used;
+		}
+	}
+
+	match count > 0.0 {
+		true => sum / count,
+		false => auto.min_log_luminance,
+	}
+}
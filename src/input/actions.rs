@@ -0,0 +1,512 @@
+use glfw::{Key, MouseButton};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs};
+
+use crate::input::{GamepadAxis, GamepadButton, Input};
+use crate::wrapper::error::InputMapError;
+
+/// Anything that can be held down.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Button {
+	Key(#[serde(with = "KeyDef")] Key),
+	/// Button1 is left, Button2 right and Button3 middle
+	Mouse(#[serde(with = "MouseButtonDef")] MouseButton),
+	Gamepad(GamepadButton),
+	/// Held while the axis is past the threshold, in the direction of its sign.
+	/// Lets triggers and sticks be used as buttons.
+	GamepadAxis(GamepadAxis, f32),
+}
+
+impl Button {
+	pub fn held(&self, input: &Input) -> bool {
+		match self {
+			Button::Key(key) => input.key_held(*key),
+			Button::Mouse(button) => input.button_held(*button),
+			Button::Gamepad(button) => input.gamepad_held(*button),
+			Button::GamepadAxis(axis, threshold) => {
+				let value = input.gamepad_axis(*axis);
+				match *threshold < 0.0 {
+					true => value <= *threshold,
+					false => value >= *threshold,
+				}
+			}
+		}
+	}
+}
+
+/// Source of an axis value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+	/// -1 while `negative` is held, 1 while `positive` is held
+	Buttons {
+		negative: Button,
+		positive: Button,
+	},
+	/// Values inside the dead zone read as 0, the rest is rescaled to start at 0
+	Gamepad {
+		axis: GamepadAxis,
+		#[serde(default)]
+		dead_zone: f32,
+		#[serde(default)]
+		invert: bool,
+	},
+	/// Cursor movement this frame in pixels times `sensitivity`
+	MouseX {
+		sensitivity: f32,
+	},
+	MouseY {
+		sensitivity: f32,
+	},
+	/// Scroll this frame times `sensitivity`
+	Scroll {
+		sensitivity: f32,
+	},
+}
+
+impl AxisBinding {
+	pub fn value(&self, input: &Input) -> f32 {
+		match self {
+			AxisBinding::Buttons { negative, positive } => {
+				positive.held(input) as i32 as f32 - negative.held(input) as i32 as f32
+			}
+			AxisBinding::Gamepad {
+				axis,
+				dead_zone,
+				invert,
+			} => {
+				let value = apply_dead_zone(input.gamepad_axis(*axis), *dead_zone);
+				match invert {
+					true => -value,
+					false => value,
+				}
+			}
+			AxisBinding::MouseX { sensitivity } => input.cursor_delta().x * sensitivity,
+			AxisBinding::MouseY { sensitivity } => input.cursor_delta().y * sensitivity,
+			AxisBinding::Scroll { sensitivity } => input.scroll().y * sensitivity,
+		}
+	}
+}
+
+/// Maps 0 at the edge of the dead zone to 1 at full deflection.
+pub fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+	let dead_zone = dead_zone.clamp(0.0, 0.99);
+	if value.abs() <= dead_zone {
+		return 0.0;
+	}
+	value.signum() * ((value.abs() - dead_zone) / (1.0 - dead_zone)).min(1.0)
+}
+
+/// Named actions and axes with their bindings, see config/input.ron
+///
+/// An action is a list of alternative chords, it is held while every button
+/// of any chord is held. An axis takes the binding with the largest value.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputMap {
+	#[serde(default)]
+	pub actions: HashMap<String, Vec<Vec<Button>>>,
+	#[serde(default)]
+	pub axes: HashMap<String, Vec<AxisBinding>>,
+}
+
+impl InputMap {
+	pub fn load(path: &str) -> Result<InputMap, InputMapError> {
+		let src = match fs::read_to_string(path) {
+			Ok(src) => src,
+			Err(e) => return Err(InputMapError::Io(path.to_owned(), e)),
+		};
+
+		match ron::from_str(&src) {
+			Ok(map) => Ok(map),
+			Err(e) => Err(InputMapError::Parse(path.to_owned(), e)),
+		}
+	}
+
+	pub fn save(&self, path: &str) -> Result<(), InputMapError> {
+		let src = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+			Ok(src) => src,
+			Err(e) => return Err(InputMapError::Serialize(path.to_owned(), e)),
+		};
+
+		match fs::write(path, src) {
+			Ok(()) => Ok(()),
+			Err(e) => Err(InputMapError::Io(path.to_owned(), e)),
+		}
+	}
+
+	/// Replaces every binding of an action with a single chord.
+	pub fn bind_action(&mut self, name: &str, chord: Vec<Button>) {
+		self.actions.insert(name.to_owned(), vec![chord]);
+	}
+
+	pub fn bind_axis(&mut self, name: &str, bindings: Vec<AxisBinding>) {
+		self.axes.insert(name.to_owned(), bindings);
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ActionState {
+	held: bool,
+	pressed: bool,
+	released: bool,
+}
+
+/// Resource with the state of every action in an `InputMap`,
+/// updated from `Input` once per frame.
+/// Unknown names read as not held and 0.
+#[derive(Default)]
+pub struct Actions {
+	pub map: InputMap,
+
+	actions: HashMap<String, ActionState>,
+	axes: HashMap<String, f32>,
+}
+
+impl Actions {
+	pub fn new(map: InputMap) -> Actions {
+		Actions {
+			map,
+			actions: HashMap::new(),
+			axes: HashMap::new(),
+		}
+	}
+
+	pub fn update(&mut self, input: &Input) {
+		for (name, chords) in &self.map.actions {
+			let held = chords
+				.iter()
+				.any(|chord| !chord.is_empty() && chord.iter().all(|button| button.held(input)));
+
+			let state = self.actions.entry(name.clone()).or_default();
+			*state = ActionState {
+				held,
+				pressed: held && !state.held,
+				released: !held && state.held,
+			};
+		}
+		// Rebound maps may have dropped actions
+		let map = &self.map;
+		self.actions
+			.retain(|name, _| map.actions.contains_key(name));
+
+		self.axes.clear();
+		for (name, bindings) in &self.map.axes {
+			let value = bindings
+				.iter()
+				.map(|binding| binding.value(input))
+				.fold(0.0, |a: f32, b| if b.abs() > a.abs() { b } else { a });
+			self.axes.insert(name.clone(), value);
+		}
+	}
+
+	/// Became held this frame
+	pub fn pressed(&self, name: &str) -> bool {
+		self.state(name).pressed
+	}
+
+	pub fn held(&self, name: &str) -> bool {
+		self.state(name).held
+	}
+
+	/// Stopped being held this frame
+	pub fn released(&self, name: &str) -> bool {
+		self.state(name).released
+	}
+
+	pub fn axis(&self, name: &str) -> f32 {
+		self.axes.get(name).copied().unwrap_or(0.0)
+	}
+
+	fn state(&self, name: &str) -> ActionState {
+		self.actions.get(name).copied().unwrap_or_default()
+	}
+}
+
+/// Serializes `glfw::Key` by variant name.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Key")]
+enum KeyDef {
+	Space,
+	Apostrophe,
+	Comma,
+	Minus,
+	Period,
+	Slash,
+	Num0,
+	Num1,
+	Num2,
+	Num3,
+	Num4,
+	Num5,
+	Num6,
+	Num7,
+	Num8,
+	Num9,
+	Semicolon,
+	Equal,
+	A,
+	B,
+	C,
+	D,
+	E,
+	F,
+	G,
+	H,
+	I,
+	J,
+	K,
+	L,
+	M,
+	N,
+	O,
+	P,
+	Q,
+	R,
+	S,
+	T,
+	U,
+	V,
+	W,
+	X,
+	Y,
+	Z,
+	LeftBracket,
+	Backslash,
+	RightBracket,
+	GraveAccent,
+	World1,
+	World2,
+	Escape,
+	Enter,
+	Tab,
+	Backspace,
+	Insert,
+	Delete,
+	Right,
+	Left,
+	Down,
+	Up,
+	PageUp,
+	PageDown,
+	Home,
+	End,
+	CapsLock,
+	ScrollLock,
+	NumLock,
+	PrintScreen,
+	Pause,
+	F1,
+	F2,
+	F3,
+	F4,
+	F5,
+	F6,
+	F7,
+	F8,
+	F9,
+	F10,
+	F11,
+	F12,
+	F13,
+	F14,
+	F15,
+	F16,
+	F17,
+	F18,
+	F19,
+	F20,
+	F21,
+	F22,
+	F23,
+	F24,
+	F25,
+	Kp0,
+	Kp1,
+	Kp2,
+	Kp3,
+	Kp4,
+	Kp5,
+	Kp6,
+	Kp7,
+	Kp8,
+	Kp9,
+	KpDecimal,
+	KpDivide,
+	KpMultiply,
+	KpSubtract,
+	KpAdd,
+	KpEnter,
+	KpEqual,
+	LeftShift,
+	LeftControl,
+	LeftAlt,
+	LeftSuper,
+	RightShift,
+	RightControl,
+	RightAlt,
+	RightSuper,
+	Menu,
+	Unknown,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "MouseButton")]
+enum MouseButtonDef {
+	Button1,
+	Button2,
+	Button3,
+	Button4,
+	Button5,
+	Button6,
+	Button7,
+	Button8,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use glfw::{Action, Modifiers, WindowEvent};
+
+	fn key(key: Key, action: Action) -> WindowEvent {
+		WindowEvent::Key(key, 0, action, Modifiers::empty())
+	}
+
+	/// Runs one frame with `events` and updates `actions` from it
+	fn frame(input: &mut Input, actions: &mut Actions, events: &[WindowEvent]) {
+		input.begin_frame();
+		for event in events {
+			input.handle_event(event);
+		}
+		actions.update(input);
+	}
+
+	fn save_chord() -> Actions {
+		let mut map = InputMap::default();
+		map.bind_action(
+			"save",
+			vec![Button::Key(Key::LeftControl), Button::Key(Key::S)],
+		);
+		Actions::new(map)
+	}
+
+	#[test]
+	fn chord_needs_every_button() {
+		let mut input = Input::new();
+		let mut actions = save_chord();
+
+		frame(&mut input, &mut actions, &[key(Key::S, Action::Press)]);
+		assert!(!actions.held("save"));
+		assert!(!actions.pressed("save"));
+
+		frame(
+			&mut input,
+			&mut actions,
+			&[key(Key::LeftControl, Action::Press)],
+		);
+		assert!(actions.held("save"));
+		assert!(actions.pressed("save"));
+	}
+
+	#[test]
+	fn pressed_and_released_edges() {
+		let mut input = Input::new();
+		let mut actions = save_chord();
+
+		frame(
+			&mut input,
+			&mut actions,
+			&[
+				key(Key::LeftControl, Action::Press),
+				key(Key::S, Action::Press),
+			],
+		);
+		assert!(actions.pressed("save"));
+		assert!(!actions.released("save"));
+
+		frame(&mut input, &mut actions, &[]);
+		assert!(actions.held("save"));
+		assert!(!actions.pressed("save"));
+
+		// Releasing any button of the chord releases the action
+		frame(&mut input, &mut actions, &[key(Key::S, Action::Release)]);
+		assert!(!actions.held("save"));
+		assert!(actions.released("save"));
+
+		frame(&mut input, &mut actions, &[]);
+		assert!(!actions.released("save"));
+		assert!(!actions.held("unknown"));
+	}
+
+	#[test]
+	fn axis_takes_the_largest_binding() {
+		let mut input = Input::new();
+		let mut map = InputMap::default();
+		map.bind_axis(
+			"forward",
+			vec![
+				AxisBinding::Buttons {
+					negative: Button::Key(Key::S),
+					positive: Button::Key(Key::W),
+				},
+				AxisBinding::Scroll { sensitivity: 0.5 },
+			],
+		);
+		let mut actions = Actions::new(map);
+
+		frame(&mut input, &mut actions, &[key(Key::S, Action::Press)]);
+		assert_eq!(actions.axis("forward"), -1.0);
+
+		frame(
+			&mut input,
+			&mut actions,
+			&[key(Key::S, Action::Release), WindowEvent::Scroll(0.0, 1.0)],
+		);
+		assert_eq!(actions.axis("forward"), 0.5);
+		assert_eq!(actions.axis("unknown"), 0.0);
+	}
+
+	#[test]
+	fn dead_zone_threshold() {
+		assert_eq!(apply_dead_zone(0.0, 0.2), 0.0);
+		assert_eq!(apply_dead_zone(0.2, 0.2), 0.0);
+		assert_eq!(apply_dead_zone(-0.2, 0.2), 0.0);
+		assert!(apply_dead_zone(0.21, 0.2) > 0.0);
+		assert!(apply_dead_zone(-0.21, 0.2) < 0.0);
+		assert_eq!(apply_dead_zone(0.625, 0.25), 0.5);
+		assert_eq!(apply_dead_zone(-1.0, 0.2), -1.0);
+		// Values past full deflection are clamped
+		assert_eq!(apply_dead_zone(1.5, 0.2), 1.0);
+		// A dead zone of 1 would divide by zero
+		assert_eq!(apply_dead_zone(1.0, 1.0), 1.0);
+	}
+
+	#[test]
+	fn ron_round_trip() {
+		let mut map = InputMap::default();
+		map.bind_action("jump", vec![Button::Key(Key::Space)]);
+		map.actions.get_mut("jump").unwrap().push(vec![
+			Button::Gamepad(GamepadButton::A),
+			Button::Mouse(MouseButton::Button1),
+			Button::GamepadAxis(GamepadAxis::RightTrigger, 0.5),
+		]);
+		map.bind_axis(
+			"look_x",
+			vec![
+				AxisBinding::MouseX { sensitivity: 0.1 },
+				AxisBinding::Gamepad {
+					axis: GamepadAxis::RightX,
+					dead_zone: 0.1,
+					invert: true,
+				},
+			],
+		);
+
+		let path = std::env::temp_dir().join(format!("input-map-{}.ron", std::process::id()));
+		let path = path.to_string_lossy().into_owned();
+		map.save(&path).unwrap();
+		let loaded = InputMap::load(&path).unwrap();
+		fs::remove_file(&path).unwrap();
+
+		assert_eq!(loaded.actions, map.actions);
+		assert_eq!(loaded.axes, map.axes);
+	}
+}
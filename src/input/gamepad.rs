@@ -0,0 +1,370 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::input::Input;
+use crate::wrapper::error::GamepadMappingError;
+
+/// Buttons of a gamepad in the standard layout, named by position
+/// on an Xbox controller. Values match the GLFW gamepad button indices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+	A = 0,
+	B = 1,
+	X = 2,
+	Y = 3,
+	LeftBumper = 4,
+	RightBumper = 5,
+	Back = 6,
+	Start = 7,
+	Guide = 8,
+	LeftThumb = 9,
+	RightThumb = 10,
+	DpadUp = 11,
+	DpadRight = 12,
+	DpadDown = 13,
+	DpadLeft = 14,
+}
+
+/// Axes of a gamepad in the standard layout.
+/// Sticks go from -1 to 1 with +y pointing down, triggers from 0 released to 1.
+/// Values match the GLFW gamepad axis indices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+	LeftX = 0,
+	LeftY = 1,
+	RightX = 2,
+	RightY = 3,
+	LeftTrigger = 4,
+	RightTrigger = 5,
+}
+
+pub const GAMEPAD_AXES: usize = 6;
+pub const GAMEPAD_BUTTONS: usize = 15;
+
+impl GamepadButton {
+	/// Every button in index order
+	pub const ALL: [GamepadButton; GAMEPAD_BUTTONS] = [
+		GamepadButton::A,
+		GamepadButton::B,
+		GamepadButton::X,
+		GamepadButton::Y,
+		GamepadButton::LeftBumper,
+		GamepadButton::RightBumper,
+		GamepadButton::Back,
+		GamepadButton::Start,
+		GamepadButton::Guide,
+		GamepadButton::LeftThumb,
+		GamepadButton::RightThumb,
+		GamepadButton::DpadUp,
+		GamepadButton::DpadRight,
+		GamepadButton::DpadDown,
+		GamepadButton::DpadLeft,
+	];
+}
+
+impl GamepadAxis {
+	/// Every axis in index order
+	pub const ALL: [GamepadAxis; GAMEPAD_AXES] = [
+		GamepadAxis::LeftX,
+		GamepadAxis::LeftY,
+		GamepadAxis::RightX,
+		GamepadAxis::RightY,
+		GamepadAxis::LeftTrigger,
+		GamepadAxis::RightTrigger,
+	];
+}
+
+/// State of one connected gamepad read from a `GamepadBackend`.
+#[derive(Clone, Debug)]
+pub struct GamepadSnapshot {
+	/// Joystick slot, stays the same while the gamepad is connected
+	pub id: usize,
+	pub name: String,
+	/// Indexed by `GamepadButton as usize`
+	pub buttons: [bool; GAMEPAD_BUTTONS],
+	/// Indexed by `GamepadAxis as usize`, raw GLFW values with triggers at -1 when released
+	pub axes: [f32; GAMEPAD_AXES],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GamepadEvent {
+	Connected { id: usize, name: String },
+	Disconnected { id: usize },
+}
+
+/// Where gamepad state comes from.
+/// `GlfwGamepads` reads real devices, tests can implement this with fake ones.
+pub trait GamepadBackend {
+	/// State of every connected gamepad
+	fn poll(&mut self) -> Vec<GamepadSnapshot>;
+
+	/// Adds mappings in the SDL_GameControllerDB format.
+	/// Returns false when they could not be parsed.
+	fn update_mappings(&mut self, mappings: &str) -> bool;
+}
+
+/// Joysticks with a gamepad mapping, through the GLFW gamepad API.
+/// Joysticks without a mapping are skipped.
+pub struct GlfwGamepads {
+	glfw: glfw::Glfw,
+}
+
+impl GlfwGamepads {
+	pub fn new(glfw: glfw::Glfw) -> GlfwGamepads {
+		GlfwGamepads { glfw }
+	}
+}
+
+impl GamepadBackend for GlfwGamepads {
+	fn poll(&mut self) -> Vec<GamepadSnapshot> {
+		let mut gamepads = Vec::new();
+		for id in 0..=glfw::ffi::JOYSTICK_LAST {
+			let joystick = match glfw::JoystickId::from_i32(id) {
+				Some(id) => self.glfw.get_joystick(id),
+				None => continue,
+			};
+			if !joystick.is_gamepad() {
+				continue;
+			}
+			let state = match joystick.get_gamepad_state() {
+				Some(state) => state,
+				None => continue,
+			};
+
+			let mut buttons = [false; GAMEPAD_BUTTONS];
+			for (i, down) in buttons.iter_mut().enumerate() {
+				if let Some(button) = glfw::GamepadButton::from_i32(i as i32) {
+					*down = state.get_button_state(button) == glfw::Action::Press;
+				}
+			}
+			let mut axes = [0.0; GAMEPAD_AXES];
+			for (i, value) in axes.iter_mut().enumerate() {
+				if let Some(axis) = glfw::GamepadAxis::from_i32(i as i32) {
+					*value = state.get_axis(axis);
+				}
+			}
+
+			gamepads.push(GamepadSnapshot {
+				id: id as usize,
+				name: joystick.get_gamepad_name().unwrap_or_default(),
+				buttons,
+				axes,
+			});
+		}
+
+		gamepads
+	}
+
+	fn update_mappings(&mut self, mappings: &str) -> bool {
+		self.glfw.update_gamepad_mappings(mappings)
+	}
+}
+
+/// Polls a `GamepadBackend` once per frame and feeds the result to `Input`.
+pub struct Gamepads {
+	backend: Box<dyn GamepadBackend>,
+}
+
+impl Gamepads {
+	pub fn new(backend: impl GamepadBackend + 'static) -> Gamepads {
+		Gamepads {
+			backend: Box::new(backend),
+		}
+	}
+
+	/// Loads a mapping database like gamecontrollerdb.txt from
+	/// https://github.com/gabomdq/SDL_GameControllerDB
+	/// GLFW has a copy built in, newer files add recent controllers.
+	pub fn load_mappings(&mut self, path: &str) -> Result<(), GamepadMappingError> {
+		let mappings = match fs::read_to_string(path) {
+			Ok(mappings) => mappings,
+			Err(e) => return Err(GamepadMappingError::Io(path.to_owned(), e)),
+		};
+
+		match self.backend.update_mappings(&mappings) {
+			true => Ok(()),
+			false => Err(GamepadMappingError::Invalid(path.to_owned())),
+		}
+	}
+
+	/// Call after `Input::begin_frame`, connection changes show up
+	/// in `Input::gamepad_events`.
+	pub fn update(&mut self, input: &mut Input) {
+		let gamepads = self.backend.poll();
+
+		let disconnected: Vec<usize> = input
+			.gamepads()
+			.map(|gamepad| gamepad.id)
+			.filter(|id| !gamepads.iter().any(|gamepad| gamepad.id == *id))
+			.collect();
+		for id in disconnected {
+			input.disconnect_gamepad(id);
+		}
+
+		for gamepad in &gamepads {
+			input.update_gamepad(gamepad);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::input::Button;
+	use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+	/// Returns one scripted list of connected gamepads per poll
+	#[derive(Clone, Default)]
+	struct FakeGamepads {
+		frames: Rc<RefCell<VecDeque<Vec<GamepadSnapshot>>>>,
+	}
+
+	impl FakeGamepads {
+		fn push(&self, gamepads: Vec<GamepadSnapshot>) {
+			self.frames.borrow_mut().push_back(gamepads);
+		}
+	}
+
+	impl GamepadBackend for FakeGamepads {
+		fn poll(&mut self) -> Vec<GamepadSnapshot> {
+			self.frames.borrow_mut().pop_front().unwrap_or_default()
+		}
+
+		fn update_mappings(&mut self, _: &str) -> bool {
+			true
+		}
+	}
+
+	fn snapshot(
+		id: usize,
+		buttons: &[GamepadButton],
+		axes: &[(GamepadAxis, f32)],
+	) -> GamepadSnapshot {
+		let mut snapshot = GamepadSnapshot {
+			id,
+			name: format!("Pad {}", id),
+			buttons: [false; GAMEPAD_BUTTONS],
+			axes: [0.0; GAMEPAD_AXES],
+		};
+		// Released triggers, like GLFW reports them
+		snapshot.axes[GamepadAxis::LeftTrigger as usize] = -1.0;
+		snapshot.axes[GamepadAxis::RightTrigger as usize] = -1.0;
+		for button in buttons {
+			snapshot.buttons[*button as usize] = true;
+		}
+		for (axis, value) in axes {
+			snapshot.axes[*axis as usize] = *value;
+		}
+		snapshot
+	}
+
+	fn frame(gamepads: &mut Gamepads, input: &mut Input) {
+		input.begin_frame();
+		gamepads.update(input);
+	}
+
+	#[test]
+	fn connect_and_disconnect_events() {
+		let fake = FakeGamepads::default();
+		let mut gamepads = Gamepads::new(fake.clone());
+		let mut input = Input::new();
+
+		fake.push(vec![snapshot(0, &[], &[])]);
+		frame(&mut gamepads, &mut input);
+		assert_eq!(
+			input.gamepad_events(),
+			&[GamepadEvent::Connected {
+				id: 0,
+				name: "Pad 0".to_owned()
+			}]
+		);
+
+		fake.push(vec![snapshot(0, &[], &[])]);
+		frame(&mut gamepads, &mut input);
+		assert!(input.gamepad_events().is_empty());
+		assert_eq!(input.gamepads().count(), 1);
+
+		fake.push(Vec::new());
+		frame(&mut gamepads, &mut input);
+		assert_eq!(
+			input.gamepad_events(),
+			&[GamepadEvent::Disconnected { id: 0 }]
+		);
+		assert!(input.gamepad(0).is_none());
+	}
+
+	#[test]
+	fn button_transitions() {
+		let fake = FakeGamepads::default();
+		let mut gamepads = Gamepads::new(fake.clone());
+		let mut input = Input::new();
+
+		fake.push(vec![snapshot(1, &[GamepadButton::A], &[])]);
+		frame(&mut gamepads, &mut input);
+		assert!(input.gamepad_pressed(GamepadButton::A));
+		assert!(input.gamepad_held(GamepadButton::A));
+
+		fake.push(vec![snapshot(1, &[GamepadButton::A], &[])]);
+		frame(&mut gamepads, &mut input);
+		assert!(!input.gamepad_pressed(GamepadButton::A));
+		assert!(input.gamepad_held(GamepadButton::A));
+
+		fake.push(vec![snapshot(1, &[], &[])]);
+		frame(&mut gamepads, &mut input);
+		assert!(!input.gamepad_held(GamepadButton::A));
+		assert!(input.gamepad_released(GamepadButton::A));
+		assert!(!input.gamepad_pressed(GamepadButton::B));
+	}
+
+	#[test]
+	fn axis_values() {
+		let fake = FakeGamepads::default();
+		let mut gamepads = Gamepads::new(fake.clone());
+		let mut input = Input::new();
+
+		fake.push(vec![
+			snapshot(0, &[], &[(GamepadAxis::LeftX, 0.25)]),
+			snapshot(
+				1,
+				&[],
+				&[
+					(GamepadAxis::LeftX, -0.75),
+					(GamepadAxis::RightTrigger, 1.0),
+				],
+			),
+		]);
+		frame(&mut gamepads, &mut input);
+
+		assert_eq!(input.gamepad(0).unwrap().axis(GamepadAxis::LeftX), 0.25);
+		assert_eq!(
+			input.gamepad(1).unwrap().axis(GamepadAxis::RightTrigger),
+			1.0
+		);
+		// The value furthest from 0 wins
+		assert_eq!(input.gamepad_axis(GamepadAxis::LeftX), -0.75);
+		assert_eq!(input.gamepad_axis(GamepadAxis::RightY), 0.0);
+	}
+
+	#[test]
+	fn idle_gamepad_reads_like_no_gamepad() {
+		let fake = FakeGamepads::default();
+		let mut gamepads = Gamepads::new(fake.clone());
+		let mut input = Input::new();
+		let trigger = Button::GamepadAxis(GamepadAxis::LeftTrigger, -0.5);
+
+		fake.push(vec![snapshot(0, &[], &[])]);
+		frame(&mut gamepads, &mut input);
+		assert_eq!(
+			input.gamepad(0).unwrap().axis(GamepadAxis::LeftTrigger),
+			0.0
+		);
+		assert_eq!(input.gamepad_axis(GamepadAxis::LeftTrigger), 0.0);
+		assert_eq!(input.gamepad_axis(GamepadAxis::RightTrigger), 0.0);
+		assert!(!trigger.held(&input));
+
+		// Half pressed is halfway from the rest value
+		fake.push(vec![snapshot(0, &[], &[(GamepadAxis::RightTrigger, 0.0)])]);
+		frame(&mut gamepads, &mut input);
+		assert_eq!(input.gamepad_axis(GamepadAxis::RightTrigger), 0.5);
+	}
+}
@@ -0,0 +1,9 @@
+mod actions;
+mod gamepad;
+mod plugin;
+mod state;
+
+pub use actions::*;
+pub use gamepad::*;
+pub use plugin::*;
+pub use state::*;
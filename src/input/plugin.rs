@@ -0,0 +1,89 @@
+use legion::Resources;
+
+use crate::engine::{App, Plugin, WindowEvents};
+use crate::input::{Actions, Gamepads, GlfwGamepads, Input, InputMap};
+use crate::wrapper::error::{GamepadMappingError, InputMapError};
+
+/// Adds the `Input` and `Actions` resources and updates them in `pre_update`.
+/// Gamepads are only polled with a window, so add the `WindowPlugin` first.
+/// Failures to load or save the input map end up in the `InputErrors` resource.
+#[derive(Clone, Debug)]
+pub struct InputPlugin {
+	/// Input map file, the "save_bindings" action writes it back
+	pub map: String,
+	/// Optional newer copy of the SDL mapping database, GLFW has one built in
+	pub gamepad_mappings: Option<String>,
+}
+
+impl Default for InputPlugin {
+	fn default() -> InputPlugin {
+		InputPlugin {
+			map: "config/input.ron".to_owned(),
+			gamepad_mappings: Some("config/gamecontrollerdb.txt".to_owned()),
+		}
+	}
+}
+
+/// Resource with the errors of the `InputPlugin`, for the game to show or log.
+#[derive(Debug, Default)]
+pub struct InputErrors {
+	/// The input map could not be loaded at startup, the default bindings are used
+	pub load: Option<InputMapError>,
+	/// The last "save_bindings" failed, cleared by the next successful save
+	pub save: Option<InputMapError>,
+	/// The gamepad mapping file could not be loaded, the built in mappings are used
+	pub gamepad_mappings: Option<GamepadMappingError>,
+}
+
+impl Plugin for InputPlugin {
+	fn build(&self, app: &mut App) {
+		let mut errors = InputErrors::default();
+
+		// Players rebind controls by editing this file
+		let input_map = match InputMap::load(&self.map) {
+			Ok(map) => map,
+			Err(e) => {
+				errors.load = Some(e);
+				InputMap::default()
+			}
+		};
+		app.resources.insert(Input::new());
+		app.resources.insert(Actions::new(input_map));
+
+		let mut gamepads = app.window.as_ref().map(|window| {
+			let mut gamepads = Gamepads::new(GlfwGamepads::new(window.glfw()));
+			if let Some(mappings) = &self.gamepad_mappings {
+				if std::path::Path::new(mappings).is_file() {
+					errors.gamepad_mappings = gamepads.load_mappings(mappings).err();
+				}
+			}
+			gamepads
+		});
+
+		let map = self.map.clone();
+		app.pre_update.add_thread_local_fn(move |_, resources| {
+			update_input(resources, gamepads.as_mut(), &map)
+		});
+		app.resources.insert(errors);
+	}
+}
+
+/// Feeds the events of this frame and the gamepads to `Input` and updates `Actions`.
+fn update_input(resources: &Resources, gamepads: Option<&mut Gamepads>, map: &str) {
+	let mut input = resources.get_mut::<Input>().unwrap();
+	input.begin_frame();
+	for event in &resources.get::<WindowEvents>().unwrap().0 {
+		input.handle_event(event);
+	}
+
+	// Connections show up in `Input::gamepad_events`
+	if let Some(gamepads) = gamepads {
+		gamepads.update(&mut input);
+	}
+
+	let mut actions = resources.get_mut::<Actions>().unwrap();
+	actions.update(&input);
+	if actions.pressed("save_bindings") {
+		resources.get_mut::<InputErrors>().unwrap().save = actions.map.save(map).err();
+	}
+}
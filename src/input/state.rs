@@ -0,0 +1,396 @@
+use glfw::{Action, Key, MouseButton, WindowEvent};
+use nalgebra::Vector2;
+use std::{
+	collections::{BTreeMap, HashSet},
+	hash::Hash,
+};
+
+use crate::input::{GamepadAxis, GamepadButton, GamepadEvent, GamepadSnapshot, GAMEPAD_AXES};
+
+/// Pressed, held and released state of a set of buttons.
+#[derive(Debug)]
+pub struct ButtonState<T: Copy + Eq + Hash> {
+	held: HashSet<T>,
+	pressed: HashSet<T>,
+	released: HashSet<T>,
+}
+
+impl<T: Copy + Eq + Hash> Default for ButtonState<T> {
+	fn default() -> Self {
+		ButtonState {
+			held: HashSet::new(),
+			pressed: HashSet::new(),
+			released: HashSet::new(),
+		}
+	}
+}
+
+impl<T: Copy + Eq + Hash> ButtonState<T> {
+	/// Went down this frame
+	pub fn pressed(&self, button: T) -> bool {
+		self.pressed.contains(&button)
+	}
+
+	/// Is down, including the frame it was pressed
+	pub fn held(&self, button: T) -> bool {
+		self.held.contains(&button)
+	}
+
+	/// Went up this frame
+	pub fn released(&self, button: T) -> bool {
+		self.released.contains(&button)
+	}
+
+	pub fn all_held(&self) -> impl Iterator<Item = &T> {
+		self.held.iter()
+	}
+
+	/// Key repeats are ignored, a button is pressed once until released.
+	fn update(&mut self, button: T, action: Action) {
+		match action {
+			Action::Press => {
+				if self.held.insert(button) {
+					self.pressed.insert(button);
+				}
+			}
+			Action::Release => {
+				if self.held.remove(&button) {
+					self.released.insert(button);
+				}
+			}
+			Action::Repeat => {}
+		}
+	}
+
+	fn begin_frame(&mut self) {
+		self.pressed.clear();
+		self.released.clear();
+	}
+
+	fn release_all(&mut self) {
+		self.released.extend(self.held.drain());
+	}
+}
+
+/// A connected gamepad.
+#[derive(Debug)]
+pub struct Gamepad {
+	pub id: usize,
+	pub name: String,
+	pub buttons: ButtonState<GamepadButton>,
+	axes: [f32; GAMEPAD_AXES],
+}
+
+impl Gamepad {
+	/// Value without a dead zone. Triggers are moved from GLFW's -1 to 1
+	/// to 0 to 1, so a released trigger reads the same as no gamepad.
+	pub fn axis(&self, axis: GamepadAxis) -> f32 {
+		let value = self.axes[axis as usize];
+		match axis {
+			GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => (value + 1.0) / 2.0,
+			_ => value,
+		}
+	}
+}
+
+/// Keyboard, mouse and gamepad state.
+/// Stored as a resource, updated at the start of every frame with
+/// `begin_frame` followed by `handle_event` for each window event of the frame
+/// and `Gamepads::update`.
+/// Events and gamepad snapshots don't need a window, so they can be constructed by hand.
+#[derive(Debug, Default)]
+pub struct Input {
+	pub keys: ButtonState<Key>,
+	pub mouse_buttons: ButtonState<MouseButton>,
+
+	gamepads: BTreeMap<usize, Gamepad>,
+	gamepad_events: Vec<GamepadEvent>,
+	cursor: Option<Vector2<f32>>,
+	cursor_delta: Vector2<f32>,
+	scroll: Vector2<f32>,
+	text: String,
+}
+
+impl Input {
+	pub fn new() -> Input {
+		Input::default()
+	}
+
+	/// Clears everything that only lasts one frame.
+	pub fn begin_frame(&mut self) {
+		self.keys.begin_frame();
+		self.mouse_buttons.begin_frame();
+		for gamepad in self.gamepads.values_mut() {
+			gamepad.buttons.begin_frame();
+		}
+		self.gamepad_events.clear();
+		self.cursor_delta = Vector2::zeros();
+		self.scroll = Vector2::zeros();
+		self.text.clear();
+	}
+
+	pub fn handle_event(&mut self, event: &WindowEvent) {
+		match event {
+			WindowEvent::Key(key, _, action, _) => self.keys.update(*key, *action),
+			WindowEvent::MouseButton(button, action, _) => {
+				self.mouse_buttons.update(*button, *action)
+			}
+			WindowEvent::CursorPos(x, y) => {
+				let position = Vector2::new(*x as f32, *y as f32);
+				// No delta for the first position, it would jump from the origin
+				if let Some(previous) = self.cursor {
+					self.cursor_delta += position - previous;
+				}
+				self.cursor = Some(position);
+			}
+			WindowEvent::Scroll(x, y) => self.scroll += Vector2::new(*x as f32, *y as f32),
+			WindowEvent::Char(c) => self.text.push(*c),
+			// Release events are lost while unfocused, so nothing stays stuck down
+			WindowEvent::Focus(false) => {
+				self.keys.release_all();
+				self.mouse_buttons.release_all();
+			}
+			_ => {}
+		}
+	}
+
+	/// Sets the state of a gamepad, connecting it if it is new.
+	pub fn update_gamepad(&mut self, snapshot: &GamepadSnapshot) {
+		let events = &mut self.gamepad_events;
+		let gamepad = self.gamepads.entry(snapshot.id).or_insert_with(|| {
+			events.push(GamepadEvent::Connected {
+				id: snapshot.id,
+				name: snapshot.name.clone(),
+			});
+			Gamepad {
+				id: snapshot.id,
+				name: snapshot.name.clone(),
+				buttons: ButtonState::default(),
+				axes: [0.0; GAMEPAD_AXES],
+			}
+		});
+
+		for (button, down) in GamepadButton::ALL.iter().zip(snapshot.buttons) {
+			let action = match down {
+				true => Action::Press,
+				false => Action::Release,
+			};
+			gamepad.buttons.update(*button, action);
+		}
+		gamepad.axes = snapshot.axes;
+	}
+
+	pub fn disconnect_gamepad(&mut self, id: usize) {
+		if self.gamepads.remove(&id).is_some() {
+			self.gamepad_events.push(GamepadEvent::Disconnected { id });
+		}
+	}
+
+	/// Connected gamepads ordered by id
+	pub fn gamepads(&self) -> impl Iterator<Item = &Gamepad> {
+		self.gamepads.values()
+	}
+
+	pub fn gamepad(&self, id: usize) -> Option<&Gamepad> {
+		self.gamepads.get(&id)
+	}
+
+	/// Connections and disconnections this frame
+	pub fn gamepad_events(&self) -> &[GamepadEvent] {
+		&self.gamepad_events
+	}
+
+	pub fn key_pressed(&self, key: Key) -> bool {
+		self.keys.pressed(key)
+	}
+
+	pub fn key_held(&self, key: Key) -> bool {
+		self.keys.held(key)
+	}
+
+	pub fn key_released(&self, key: Key) -> bool {
+		self.keys.released(key)
+	}
+
+	pub fn button_pressed(&self, button: MouseButton) -> bool {
+		self.mouse_buttons.pressed(button)
+	}
+
+	pub fn button_held(&self, button: MouseButton) -> bool {
+		self.mouse_buttons.held(button)
+	}
+
+	pub fn button_released(&self, button: MouseButton) -> bool {
+		self.mouse_buttons.released(button)
+	}
+
+	/// Pressed on any gamepad
+	pub fn gamepad_pressed(&self, button: GamepadButton) -> bool {
+		self.gamepads()
+			.any(|gamepad| gamepad.buttons.pressed(button))
+	}
+
+	pub fn gamepad_held(&self, button: GamepadButton) -> bool {
+		self.gamepads().any(|gamepad| gamepad.buttons.held(button))
+	}
+
+	pub fn gamepad_released(&self, button: GamepadButton) -> bool {
+		self.gamepads()
+			.any(|gamepad| gamepad.buttons.released(button))
+	}
+
+	/// Value furthest from 0 over every gamepad, without a dead zone
+	pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+		self.gamepads()
+			.map(|gamepad| gamepad.axis(axis))
+			.fold(0.0, |a: f32, b| if b.abs() > a.abs() { b } else { a })
+	}
+
+	/// Cursor position in screen coordinates from the top left,
+	/// zero until the cursor has moved over the window.
+	pub fn cursor_position(&self) -> Vector2<f32> {
+		self.cursor.unwrap_or_else(Vector2::zeros)
+	}
+
+	/// Cursor movement since the last frame
+	pub fn cursor_delta(&self) -> Vector2<f32> {
+		self.cursor_delta
+	}
+
+	/// Scroll since the last frame, y is the usual mouse wheel
+	pub fn scroll(&self) -> Vector2<f32> {
+		self.scroll
+	}
+
+	/// Characters typed since the last frame
+	pub fn text(&self) -> &str {
+		&self.text
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use glfw::Modifiers;
+
+	fn key(key: Key, action: Action) -> WindowEvent {
+		WindowEvent::Key(key, 0, action, Modifiers::empty())
+	}
+
+	/// Runs one frame with `events`
+	fn frame(input: &mut Input, events: &[WindowEvent]) {
+		input.begin_frame();
+		for event in events {
+			input.handle_event(event);
+		}
+	}
+
+	#[test]
+	fn pressed_held_released() {
+		let mut input = Input::new();
+
+		frame(&mut input, &[key(Key::W, Action::Press)]);
+		assert!(input.key_pressed(Key::W));
+		assert!(input.key_held(Key::W));
+		assert!(!input.key_released(Key::W));
+
+		frame(&mut input, &[]);
+		assert!(!input.key_pressed(Key::W));
+		assert!(input.key_held(Key::W));
+
+		frame(&mut input, &[key(Key::W, Action::Release)]);
+		assert!(!input.key_held(Key::W));
+		assert!(input.key_released(Key::W));
+
+		frame(&mut input, &[]);
+		assert!(!input.key_released(Key::W));
+	}
+
+	#[test]
+	fn press_and_release_in_one_frame() {
+		let mut input = Input::new();
+		frame(
+			&mut input,
+			&[
+				key(Key::Space, Action::Press),
+				key(Key::Space, Action::Release),
+			],
+		);
+		assert!(input.key_pressed(Key::Space));
+		assert!(input.key_released(Key::Space));
+		assert!(!input.key_held(Key::Space));
+	}
+
+	#[test]
+	fn repeat_is_ignored() {
+		let mut input = Input::new();
+		frame(&mut input, &[key(Key::A, Action::Press)]);
+		frame(&mut input, &[key(Key::A, Action::Repeat)]);
+		assert!(!input.key_pressed(Key::A));
+		assert!(input.key_held(Key::A));
+
+		// A repeat without a press doesn't hold the key down
+		frame(&mut input, &[key(Key::B, Action::Repeat)]);
+		assert!(!input.key_pressed(Key::B));
+		assert!(!input.key_held(Key::B));
+	}
+
+	#[test]
+	fn cursor_delta_resets_each_frame() {
+		let mut input = Input::new();
+		frame(&mut input, &[WindowEvent::CursorPos(10.0, 10.0)]);
+		assert_eq!(input.cursor_delta(), Vector2::zeros());
+		assert_eq!(input.cursor_position(), Vector2::new(10.0, 10.0));
+
+		frame(
+			&mut input,
+			&[
+				WindowEvent::CursorPos(12.0, 9.0),
+				WindowEvent::CursorPos(15.0, 5.0),
+			],
+		);
+		assert_eq!(input.cursor_delta(), Vector2::new(5.0, -5.0));
+
+		frame(&mut input, &[]);
+		assert_eq!(input.cursor_delta(), Vector2::zeros());
+		assert_eq!(input.cursor_position(), Vector2::new(15.0, 5.0));
+	}
+
+	#[test]
+	fn scroll_and_text_accumulate() {
+		let mut input = Input::new();
+		frame(
+			&mut input,
+			&[
+				WindowEvent::Scroll(0.0, 1.0),
+				WindowEvent::Scroll(0.5, 2.0),
+				WindowEvent::Char('h'),
+				WindowEvent::Char('i'),
+			],
+		);
+		assert_eq!(input.scroll(), Vector2::new(0.5, 3.0));
+		assert_eq!(input.text(), "hi");
+
+		frame(&mut input, &[]);
+		assert_eq!(input.scroll(), Vector2::zeros());
+		assert_eq!(input.text(), "");
+	}
+
+	#[test]
+	fn focus_loss_releases_everything() {
+		let mut input = Input::new();
+		frame(
+			&mut input,
+			&[
+				key(Key::W, Action::Press),
+				WindowEvent::MouseButton(MouseButton::Button1, Action::Press, Modifiers::empty()),
+			],
+		);
+
+		frame(&mut input, &[WindowEvent::Focus(false)]);
+		assert!(!input.key_held(Key::W));
+		assert!(input.key_released(Key::W));
+		assert!(!input.button_held(MouseButton::Button1));
+		assert!(input.button_released(MouseButton::Button1));
+	}
+}
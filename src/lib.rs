@@ -0,0 +1,7 @@
+extern crate nalgebra_glm as glm;
+
+pub mod components;
+pub mod engine;
+pub mod input;
+pub mod util;
+pub mod wrapper;
@@ -2,7 +2,7 @@ use std::error::Error;
 use std::ffi::CString;
 use std::fs;
 
-use nalgebra::{vector, Vector3};
+use nalgebra::{vector, Matrix4, Point3, Vector3};
 
 pub fn load_to_string(path: &str) -> Result<String, Box<dyn Error>> {
 	let content = fs::read_to_string(path)?;
@@ -63,3 +63,23 @@ pub fn ping_pong(t: f32, length: f32) -> f32 {
 pub fn repeat(t: f32, length: f32) -> f32 {
 	return t - (t / length).floor() * length;
 }
+
+/// View matrices looking from `eye` through each cube map face,
+/// ordered +X, -X, +Y, -Y, +Z, -Z.
+pub fn cube_face_views(eye: &Point3<f32>) -> [Matrix4<f32>; 6] {
+	let faces = [
+		(Vector3::x(), -Vector3::y()),
+		(-Vector3::x(), -Vector3::y()),
+		(Vector3::y(), Vector3::z()),
+		(-Vector3::y(), -Vector3::z()),
+		(Vector3::z(), -Vector3::y()),
+		(-Vector3::z(), -Vector3::y()),
+	];
+
+	let mut views = [Matrix4::identity(); 6];
+	for (i, (direction, up)) in faces.iter().enumerate() {
+		views[i] = Matrix4::look_at_rh(eye, &(eye + direction), up);
+	}
+
+	views
+}
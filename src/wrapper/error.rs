@@ -19,6 +19,15 @@ pub enum GLError {
 
 pub type ShaderError = String;
 
+#[derive(Debug)]
+pub enum LoaderError {
+	Gltf(gltf::Error),
+	MissingPositions(usize),
+	UnsupportedImageFormat(gltf::image::Format),
+	Texture(String),
+	GL(GLError),
+}
+
 impl Display for GLError {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match self {
@@ -34,6 +43,150 @@ impl Display for GLError {
 	}
 }
 
+#[derive(Debug)]
+pub enum MaterialError {
+	Io(String, std::io::Error),
+	Parse(String, ron::Error),
+	MissingShader(String),
+	Shader(ShaderError),
+	UnknownUniform {
+		material: String,
+		uniform: String,
+	},
+	TypeMismatch {
+		material: String,
+		uniform: String,
+		expected: &'static str,
+	},
+}
+
+impl Display for LoaderError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			LoaderError::Gltf(e) => write!(f, "glTF: {}", e),
+			LoaderError::MissingPositions(mesh) => {
+				write!(f, "Mesh {} has a primitive without positions", mesh)
+			}
+			LoaderError::UnsupportedImageFormat(format) => {
+				write!(f, "Unsupported image format: {:?}", format)
+			}
+			LoaderError::Texture(e) => write!(f, "Texture: {}", e),
+			LoaderError::GL(e) => write!(f, "GL: {}", e),
+		}
+	}
+}
+
+impl From<gltf::Error> for LoaderError {
+	fn from(e: gltf::Error) -> Self {
+		LoaderError::Gltf(e)
+	}
+}
+
+impl From<GLError> for LoaderError {
+	fn from(e: GLError) -> Self {
+		LoaderError::GL(e)
+	}
+}
+
+impl Display for MaterialError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			MaterialError::Io(path, e) => write!(f, "{}: {}", path, e),
+			MaterialError::Parse(path, e) => write!(f, "{}: {}", path, e),
+			MaterialError::MissingShader(path) => write!(f, "Shader file not found: {}", path),
+			MaterialError::Shader(e) => write!(f, "Shader: {}", e),
+			MaterialError::UnknownUniform { material, uniform } => write!(
+				f,
+				"Material {}: shader has no uniform named {}",
+				material, uniform
+			),
+			MaterialError::TypeMismatch {
+				material,
+				uniform,
+				expected,
+			} => write!(
+				f,
+				"Material {}: uniform {} expects a value of type {}",
+				material, uniform, expected
+			),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum EnvironmentError {
+	Image(String, image::ImageError),
+	Shader(ShaderError),
+	Framebuffer(String),
+	Cache(String, std::io::Error),
+}
+
+impl Display for EnvironmentError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			EnvironmentError::Image(path, e) => write!(f, "{}: {}", path, e),
+			EnvironmentError::Shader(e) => write!(f, "Shader: {}", e),
+			EnvironmentError::Framebuffer(e) => write!(f, "Framebuffer: {}", e),
+			EnvironmentError::Cache(path, e) => write!(f, "Cache {}: {}", path, e),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum LutError {
+	Io(String, std::io::Error),
+	Parse {
+		path: String,
+		line: usize,
+		message: &'static str,
+	},
+}
+
+impl Display for LutError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			LutError::Io(path, e) => write!(f, "{}: {}", path, e),
+			LutError::Parse {
+				path,
+				line,
+				message,
+			} => write!(f, "{}:{}: {}", path, line, message),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum InputMapError {
+	Io(String, std::io::Error),
+	Parse(String, ron::Error),
+	Serialize(String, ron::Error),
+}
+
+impl Display for InputMapError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			InputMapError::Io(path, e) => write!(f, "{}: {}", path, e),
+			InputMapError::Parse(path, e) => write!(f, "{}: {}", path, e),
+			InputMapError::Serialize(path, e) => write!(f, "{}: could not serialize: {}", path, e),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum GamepadMappingError {
+	Io(String, std::io::Error),
+	Invalid(String),
+}
+
+impl Display for GamepadMappingError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			GamepadMappingError::Io(path, e) => write!(f, "{}: {}", path, e),
+			GamepadMappingError::Invalid(path) => write!(f, "{}: invalid gamepad mappings", path),
+		}
+	}
+}
+
 pub fn get_error() -> Option<GLError> {
 	unsafe {
 		let err = gl::GetError();
@@ -55,7 +208,7 @@ pub fn match_error(err: GLenum) -> Option<GLError> {
 	}
 }
 
-pub extern "system" fn error_callback(
+pub(crate) extern "system" fn error_callback(
 	source_: GLenum,
 	gltype_: GLenum,
 	id: GLuint,
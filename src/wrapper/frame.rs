@@ -1,4 +1,12 @@
 use gl::types::*;
+use std::{
+	ffi::CStr,
+	os::raw::c_char,
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Set once the context is known to support glClipControl
+static CLIP_CONTROL: AtomicBool = AtomicBool::new(false);
 
 /// This has safe versions of gl rendering calls
 pub struct Frame {}
@@ -20,3 +28,107 @@ impl Frame {
 		}
 	}
 }
+
+/// Size of the window's framebuffer in pixels.
+/// Stored as a resource, cameras follow its aspect ratio.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+	pub width: u32,
+	pub height: u32,
+}
+
+impl Viewport {
+	/// Width / height, 1 while minimized
+	pub fn aspect_ratio(&self) -> f32 {
+		if self.width == 0 || self.height == 0 {
+			return 1.0;
+		}
+		self.width as f32 / self.height as f32
+	}
+}
+
+/// Which end of the depth buffer is nearest to the camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepthRange {
+	/// 0 near, 1 far
+	Standard,
+	/// 1 near, 0 far, for `Projection::InfinitePerspective`.
+	/// Needs glClipControl, see `DepthRange::reversed_supported`.
+	Reversed,
+}
+
+impl DepthRange {
+	/// Checks whether the current context has glClipControl, from GL 4.5 or
+	/// ARB_clip_control. The function pointer alone says nothing, drivers load it
+	/// for contexts that can't use it. Called once after the context is created.
+	pub fn detect_support() {
+		let supported = gl::ClipControl::is_loaded() && unsafe { has_clip_control() };
+		CLIP_CONTROL.store(supported, Ordering::Relaxed);
+	}
+
+	/// Whether reversed depth can be used, false before `detect_support`.
+	/// Cameras with an infinite far plane fall back to standard depth without it.
+	pub fn reversed_supported() -> bool {
+		CLIP_CONTROL.load(Ordering::Relaxed)
+	}
+
+	/// Sets the clip depth range and the clear depth, call before clearing.
+	/// Reversed depth maps clip z 0..1 straight to depth with glClipControl.
+	pub fn apply(&self) {
+		unsafe {
+			if DepthRange::reversed_supported() {
+				let depth = match self {
+					DepthRange::Standard => gl::NEGATIVE_ONE_TO_ONE,
+					DepthRange::Reversed => gl::ZERO_TO_ONE,
+				};
+				gl::ClipControl(gl::LOWER_LEFT, depth);
+			}
+			gl::ClearDepth(self.far_depth() as f64);
+		}
+	}
+
+	/// Turns a comparison written for standard depth around, LESS becomes GREATER.
+	pub fn func(&self, func: GLenum) -> GLenum {
+		match (self, func) {
+			(DepthRange::Standard, _) => func,
+			(DepthRange::Reversed, gl::LESS) => gl::GREATER,
+			(DepthRange::Reversed, gl::LEQUAL) => gl::GEQUAL,
+			(DepthRange::Reversed, gl::GREATER) => gl::LESS,
+			(DepthRange::Reversed, gl::GEQUAL) => gl::LEQUAL,
+			(DepthRange::Reversed, _) => func,
+		}
+	}
+
+	/// Value in the depth buffer for the far plane
+	pub fn far_depth(&self) -> f32 {
+		match self {
+			DepthRange::Standard => 1.0,
+			DepthRange::Reversed => 0.0,
+		}
+	}
+
+	/// Normalized device z of the far plane, infinitely far for reversed depth
+	pub fn far_ndc(&self) -> f32 {
+		match self {
+			DepthRange::Standard => 1.0,
+			DepthRange::Reversed => 0.0,
+		}
+	}
+}
+
+unsafe fn has_clip_control() -> bool {
+	let (mut major, mut minor) = (0, 0);
+	gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+	gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+	if (major, minor) >= (4, 5) {
+		return true;
+	}
+
+	let mut count = 0;
+	gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+	(0..count.max(0) as u32).any(|i| {
+		let name = gl::GetStringi(gl::EXTENSIONS, i);
+		!name.is_null()
+			&& CStr::from_ptr(name as *const c_char).to_bytes() == b"GL_ARB_clip_control"
+	})
+}
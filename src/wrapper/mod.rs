@@ -16,13 +16,14 @@ pub mod render {
 	/// Contains core modules for rendering
 	pub mod core {
 		pub use super::super::rendering::{
-			mesh, shader, Loader, Material, Texture, TextureOptions, UniformManager,
+			mesh, shader, BlendMode, GltfLoader, GltfNode, GltfScene, Loader, Material,
+			MaterialManager, MaterialMaps, Texture, TextureOptions, UniformManager, UniformValue,
 		};
 	}
 
 	/// Contains buffer object modules
 	pub mod buffers {
-		pub use super::super::rendering::{FrameBuffer, RenderBuffer, UniformBuffer};
+		pub use super::super::rendering::{FrameBuffer, PixelBuffer, RenderBuffer, UniformBuffer};
 	}
 
 	pub mod primitive {
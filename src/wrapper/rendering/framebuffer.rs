@@ -1,22 +1,20 @@
-use crate::wrapper::render::core::Texture;
+use crate::wrapper::render::{buffers::RenderBuffer, core::Texture};
 
 use gl::types::*;
 use std::collections::HashMap;
 
-pub struct TextureBuffer {
-	pub name: String,
-	pub width: u32,
-	pub height: u32,
-	pub type_: GLenum,
-	pub internal_format: GLenum,
-	pub format: GLenum,
-	pub attachment: GLenum,
-}
-
 pub struct FrameBuffer {
 	pub fbo: u32,
 	pub buffers: HashMap<String, Texture>,
 	pub attachments: Vec<GLenum>,
+	pub depth: Option<Texture>,
+	pub depth_buffer: Option<RenderBuffer>,
+}
+
+impl Default for FrameBuffer {
+	fn default() -> Self {
+		FrameBuffer::new()
+	}
 }
 
 impl FrameBuffer {
@@ -25,6 +23,8 @@ impl FrameBuffer {
 			fbo: 0,
 			buffers: HashMap::default(),
 			attachments: Vec::new(),
+			depth: None,
+			depth_buffer: None,
 		};
 
 		unsafe {
@@ -73,6 +73,111 @@ impl FrameBuffer {
 		}
 	}
 
+	/// Stores a depth texture created with `Texture::depth_for_framebuffer`.
+	pub fn set_depth_texture(&mut self, texture: Texture) {
+		self.depth = Some(texture);
+	}
+
+	/// Stores a depth buffer created with `RenderBuffer::new`.
+	pub fn set_depth_buffer(&mut self, buffer: RenderBuffer) {
+		self.depth_buffer = Some(buffer);
+	}
+
+	/// Reallocates every stored texture and the depth buffer with a new size.
+	/// Textures attached with the `attach_` functions but not stored are left alone.
+	pub fn resize(&mut self, width: u32, height: u32) {
+		for texture in self.buffers.values() {
+			texture.resize(width, height);
+		}
+		if let Some(depth) = &self.depth {
+			depth.resize(width, height);
+		}
+		if let Some(buffer) = &mut self.depth_buffer {
+			buffer.resize(width, height);
+		}
+	}
+
+	/// Attaches one layer of an array texture as depth buffer.
+	/// The framebuffer must be bound.
+	pub fn attach_depth_layer(&self, texture: &Texture, layer: u32) {
+		unsafe {
+			gl::FramebufferTextureLayer(
+				gl::FRAMEBUFFER,
+				gl::DEPTH_ATTACHMENT,
+				texture.id,
+				0,
+				layer as i32,
+			);
+		}
+	}
+
+	/// Attaches one face of a cube map as depth buffer, faces are ordered +X, -X, +Y, -Y, +Z, -Z.
+	/// The framebuffer must be bound.
+	pub fn attach_depth_cube_face(&self, texture: &Texture, face: u32) {
+		unsafe {
+			gl::FramebufferTexture2D(
+				gl::FRAMEBUFFER,
+				gl::DEPTH_ATTACHMENT,
+				gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+				texture.id,
+				0,
+			);
+		}
+	}
+
+	/// Attaches a mip level of a 2D texture as the first color buffer.
+	/// The framebuffer must be bound.
+	pub fn attach_color_texture(&self, texture: &Texture, mip: u32) {
+		unsafe {
+			gl::FramebufferTexture2D(
+				gl::FRAMEBUFFER,
+				gl::COLOR_ATTACHMENT0,
+				gl::TEXTURE_2D,
+				texture.id,
+				mip as i32,
+			);
+		}
+	}
+
+	/// Attaches a mip level of one cube map face as the first color buffer.
+	/// The framebuffer must be bound.
+	pub fn attach_color_cube_face(&self, texture: &Texture, face: u32, mip: u32) {
+		unsafe {
+			gl::FramebufferTexture2D(
+				gl::FRAMEBUFFER,
+				gl::COLOR_ATTACHMENT0,
+				gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+				texture.id,
+				mip as i32,
+			);
+		}
+	}
+
+	/// Disables color output, for depth only framebuffers.
+	pub fn no_color_buffers(&self) {
+		unsafe {
+			gl::DrawBuffer(gl::NONE);
+			gl::ReadBuffer(gl::NONE);
+		}
+	}
+
+	/// Frees the framebuffer and every texture attached to it.
+	pub fn delete(&self) {
+		for texture in self.buffers.values() {
+			texture.delete();
+		}
+		if let Some(depth) = &self.depth {
+			depth.delete();
+		}
+		if let Some(buffer) = &self.depth_buffer {
+			buffer.delete();
+		}
+
+		unsafe {
+			gl::DeleteFramebuffers(1, &self.fbo);
+		}
+	}
+
 	pub fn get_buffer(&self, name: &str) -> &Texture {
 		return self.buffers.get(name).unwrap();
 	}
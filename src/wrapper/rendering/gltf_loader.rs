@@ -0,0 +1,230 @@
+use crate::{
+	util::calculate_normals,
+	wrapper::{
+		error::LoaderError,
+		render::core::{
+			mesh::{Mesh, Vertex},
+			shader::Shader,
+			BlendMode, Material, MaterialMaps, Texture, TextureOptions,
+		},
+	},
+};
+use gltf::{image::Format, material::AlphaMode};
+use nalgebra::{vector, Quaternion, Rotation3, UnitQuaternion, Vector2, Vector3};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A single drawable part of a glTF mesh.
+#[derive(Clone)]
+pub struct GltfPrimitive {
+	pub mesh: Mesh,
+	pub material: Material,
+}
+
+/// A glTF node with its local transform and children.
+#[derive(Clone)]
+pub struct GltfNode {
+	pub name: Option<String>,
+
+	pub position: Vector3<f32>,
+	pub rotation: Rotation3<f32>,
+	pub scale: Vector3<f32>,
+
+	pub primitives: Vec<GltfPrimitive>,
+	pub children: Vec<GltfNode>,
+}
+
+/// Root nodes of the default scene in a glTF file.
+#[derive(Clone)]
+pub struct GltfScene {
+	pub nodes: Vec<GltfNode>,
+}
+
+pub struct GltfLoader {
+	shader: Shader,
+	buffers: Vec<gltf::buffer::Data>,
+	images: Vec<gltf::image::Data>,
+
+	/// Keyed by image index and whether it was uploaded as sRGB
+	textures_loaded: HashMap<(usize, bool), Texture>,
+}
+
+impl GltfLoader {
+	/// Loads a .gltf or .glb file.
+	/// Embedded and external buffers are both resolved relative to the file.
+	/// Every material in the scene is created with `shader`.
+	pub fn load(path: &str, shader: Shader) -> Result<GltfScene, LoaderError> {
+		let (document, buffers, images) = gltf::import(Path::new(path))?;
+
+		let mut loader = GltfLoader {
+			shader,
+			buffers,
+			images,
+			textures_loaded: HashMap::new(),
+		};
+
+		let scene = match document.default_scene() {
+			Some(scene) => Some(scene),
+			None => document.scenes().next(),
+		};
+
+		let mut nodes = Vec::new();
+		if let Some(scene) = scene {
+			for node in scene.nodes() {
+				nodes.push(loader.load_node(&node)?);
+			}
+		}
+
+		Ok(GltfScene { nodes })
+	}
+
+	fn load_node(&mut self, node: &gltf::Node) -> Result<GltfNode, LoaderError> {
+		let (translation, rotation, scale) = node.transform().decomposed();
+		let [x, y, z, w] = rotation;
+		let rotation = UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z));
+
+		let mut primitives = Vec::new();
+		if let Some(mesh) = node.mesh() {
+			for primitive in mesh.primitives() {
+				primitives.push(self.load_primitive(mesh.index(), &primitive)?);
+			}
+		}
+
+		let mut children = Vec::new();
+		for child in node.children() {
+			children.push(self.load_node(&child)?);
+		}
+
+		Ok(GltfNode {
+			name: node.name().map(|n| n.to_owned()),
+			position: Vector3::from(translation),
+			rotation: rotation.to_rotation_matrix(),
+			scale: Vector3::from(scale),
+			primitives,
+			children,
+		})
+	}
+
+	fn load_primitive(
+		&mut self,
+		mesh_index: usize,
+		primitive: &gltf::Primitive,
+	) -> Result<GltfPrimitive, LoaderError> {
+		let buffers = &self.buffers;
+		let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+		let positions: Vec<Vector3<f32>> = match reader.read_positions() {
+			Some(iter) => iter.map(Vector3::from).collect(),
+			None => return Err(LoaderError::MissingPositions(mesh_index)),
+		};
+
+		// Non-indexed primitives draw their vertices in order
+		let indices: Vec<u32> = match reader.read_indices() {
+			Some(iter) => iter.into_u32().collect(),
+			None => (0..positions.len() as u32).collect(),
+		};
+
+		// Create or get normal vector
+		let normals: Vec<Vector3<f32>> = match reader.read_normals() {
+			Some(iter) => iter.map(Vector3::from).collect(),
+			None => calculate_normals(&positions, &indices),
+		};
+
+		let tex_coords: Vec<Vector2<f32>> = match reader.read_tex_coords(0) {
+			Some(iter) => iter.into_f32().map(Vector2::from).collect(),
+			None => vec![Vector2::default(); positions.len()],
+		};
+
+		let mut vertices: Vec<Vertex> = Vec::with_capacity(positions.len());
+		for i in 0..positions.len() {
+			vertices.push(Vertex {
+				position: positions[i],
+				normal: normals[i],
+				tex_coords: tex_coords[i],
+			});
+		}
+
+		let mesh = Mesh::new(vertices, indices, Vec::new())?;
+		let material = self.load_material(&primitive.material())?;
+
+		Ok(GltfPrimitive { mesh, material })
+	}
+
+	/// Maps the metallic-roughness model onto `Material`.
+	fn load_material(&mut self, material: &gltf::Material) -> Result<Material, LoaderError> {
+		let pbr = material.pbr_metallic_roughness();
+		let [r, g, b, a] = pbr.base_color_factor();
+
+		let mut maps = MaterialMaps::default();
+		if let Some(info) = pbr.base_color_texture() {
+			maps.albedo = Some(self.load_texture(&info.texture(), "albedo_map", true)?);
+		}
+		if let Some(info) = pbr.metallic_roughness_texture() {
+			maps.metallic_roughness =
+				Some(self.load_texture(&info.texture(), "metallic_roughness_map", false)?);
+		}
+		if let Some(info) = material.occlusion_texture() {
+			maps.ao = Some(self.load_texture(&info.texture(), "ao_map", false)?);
+		}
+
+		// Cutoff defaults to 0.5 in the glTF spec
+		let blend = match material.alpha_mode() {
+			AlphaMode::Opaque => BlendMode::Opaque,
+			AlphaMode::Mask => BlendMode::AlphaTest(material.alpha_cutoff().unwrap_or(0.5)),
+			AlphaMode::Blend => BlendMode::AlphaBlend,
+		};
+
+		Ok(Material {
+			shader: self.shader,
+			albedo: vector!(r, g, b),
+			metallic: pbr.metallic_factor(),
+			roughness: pbr.roughness_factor(),
+			ao: 1.0,
+			alpha: a,
+			blend,
+			maps,
+			uniforms: Vec::new(),
+		})
+	}
+
+	/// Color maps are stored as sRGB in glTF, `srgb` makes GL convert them to linear
+	/// when sampled. Data maps like metallic-roughness stay linear.
+	fn load_texture(
+		&mut self,
+		texture: &gltf::Texture,
+		type_name: &str,
+		srgb: bool,
+	) -> Result<Texture, LoaderError> {
+		// check for duplicates, an image used as color and data is uploaded twice
+		let index = texture.source().index();
+		if let Some(texture) = self.textures_loaded.get(&(index, srgb)) {
+			return Ok(texture.clone());
+		}
+
+		let image = &self.images[index];
+		let (internal_format, format) = match (image.format, srgb) {
+			(Format::R8, _) => (gl::RED, gl::RED),
+			(Format::R8G8, _) => (gl::RG, gl::RG),
+			(Format::R8G8B8, false) => (gl::RGB, gl::RGB),
+			(Format::R8G8B8, true) => (gl::SRGB8, gl::RGB),
+			(Format::R8G8B8A8, false) => (gl::RGBA, gl::RGBA),
+			(Format::R8G8B8A8, true) => (gl::SRGB8_ALPHA8, gl::RGBA),
+			(format, _) => return Err(LoaderError::UnsupportedImageFormat(format)),
+		};
+
+		// glTF texture coordinates start at the top left,
+		// so the rows are uploaded without flipping.
+		let options = TextureOptions {
+			width: image.width,
+			height: image.height,
+			internal_format,
+			format,
+			type_: gl::UNSIGNED_BYTE,
+		};
+		let texture =
+			Texture::from_data(type_name, &options, &image.pixels).map_err(LoaderError::Texture)?;
+
+		self.textures_loaded.insert((index, srgb), texture.clone());
+		Ok(texture)
+	}
+}
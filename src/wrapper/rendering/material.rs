@@ -1,5 +1,55 @@
-use crate::wrapper::render::core::shader::Shader;
+use crate::wrapper::render::core::{shader::Shader, Texture};
 use nalgebra::Vector3;
+use serde::Deserialize;
+
+/// First texture unit used by material maps.
+/// Units below this are left for mesh textures.
+const MAP_UNIT: u32 = 4;
+
+/// Optional textures that modulate the material factors.
+#[derive(Clone, Default)]
+pub struct MaterialMaps {
+	pub albedo: Option<Texture>,
+	/// Roughness in the green channel, metallic in the blue channel.
+	pub metallic_roughness: Option<Texture>,
+	/// Ambient occlusion in the red channel.
+	pub ao: Option<Texture>,
+}
+
+/// How a material is combined with what is behind it.
+/// Opaque and alpha tested materials go through the deferred geometry pass,
+/// blended ones are drawn afterwards by the forward pass.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+pub enum BlendMode {
+	#[default]
+	Opaque,
+	/// Discards fragments with alpha below the cutoff
+	AlphaTest(f32),
+	/// Mixes with the background by alpha
+	AlphaBlend,
+	/// Adds onto the background, scaled by alpha
+	Additive,
+	/// Order independent blending, for surfaces that intersect or can't be sorted
+	WeightedBlended,
+}
+
+impl BlendMode {
+	/// True for modes drawn by the forward pass.
+	pub fn is_blended(&self) -> bool {
+		matches!(
+			self,
+			BlendMode::AlphaBlend | BlendMode::Additive | BlendMode::WeightedBlended
+		)
+	}
+}
+
+/// Extra uniform set by `Material::use_material`.
+#[derive(Clone, Debug)]
+pub enum UniformValue {
+	Int(i32),
+	Float(f32),
+	Vec3(Vector3<f32>),
+}
 
 #[derive(Clone)]
 pub struct Material {
@@ -9,6 +59,12 @@ pub struct Material {
 	pub metallic: f32,
 	pub roughness: f32,
 	pub ao: f32,
+	/// Opacity, multiplied with the alpha of the albedo map
+	pub alpha: f32,
+	pub blend: BlendMode,
+
+	pub maps: MaterialMaps,
+	pub uniforms: Vec<(String, UniformValue)>,
 }
 
 impl Material {
@@ -25,13 +81,58 @@ impl Material {
 			metallic,
 			roughness,
 			ao,
+			alpha: 1.0,
+			blend: BlendMode::Opaque,
+			maps: MaterialMaps::default(),
+			uniforms: Vec::new(),
 		}
 	}
 
 	pub fn use_material(&self) {
-		self.shader.set_vector3("material_albedo", &self.albedo);
-		self.shader.set_float("material_metallic", self.metallic);
-		self.shader.set_float("material_roughness", self.roughness);
-		self.shader.set_float("material_ao", self.ao);
+		self.use_with(&self.shader, MAP_UNIT);
+	}
+
+	/// Sets the material on another shader, like the forward pass shader,
+	/// with the maps bound from `map_unit` on.
+	pub fn use_with(&self, shader: &Shader, map_unit: u32) {
+		shader.set_vector3("material_albedo", &self.albedo);
+		shader.set_float("material_metallic", self.metallic);
+		shader.set_float("material_roughness", self.roughness);
+		shader.set_float("material_ao", self.ao);
+		shader.set_float("material_alpha", self.alpha);
+		let cutoff = match self.blend {
+			BlendMode::AlphaTest(cutoff) => cutoff,
+			_ => 0.0,
+		};
+		shader.set_float("alpha_cutoff", cutoff);
+
+		use_map(shader, "albedo_map", &self.maps.albedo, map_unit);
+		use_map(
+			shader,
+			"metallic_roughness_map",
+			&self.maps.metallic_roughness,
+			map_unit + 1,
+		);
+		use_map(shader, "ao_map", &self.maps.ao, map_unit + 2);
+
+		for (name, value) in &self.uniforms {
+			match value {
+				UniformValue::Int(v) => shader.set_int(name, *v),
+				UniformValue::Float(v) => shader.set_float(name, *v),
+				UniformValue::Vec3(v) => shader.set_vector3(name, v),
+			}
+		}
+	}
+}
+
+fn use_map(shader: &Shader, name: &str, map: &Option<Texture>, unit: u32) {
+	let flag = format!("use_{}", name);
+	match map {
+		Some(texture) => {
+			shader.set_int(&flag, 1);
+			shader.set_int(name, unit as i32);
+			texture.bind_unit(unit);
+		}
+		None => shader.set_int(&flag, 0),
 	}
 }
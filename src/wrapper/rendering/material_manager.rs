@@ -1 +1,223 @@
-struct MaterialManager {}
+use crate::wrapper::{
+	error::MaterialError,
+	render::core::{shader::Shader, BlendMode, Material, UniformValue},
+};
+use gl::types::*;
+use nalgebra::{vector, Vector3};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+
+/// Uniform value as written in a material file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RonValue {
+	Number(f64),
+	Tuple(Vec<f32>),
+}
+
+/// Layout of a material file, see materials/green.ron
+#[derive(Deserialize)]
+#[serde(rename = "Material")]
+struct MaterialAsset {
+	name: String,
+	shader: (String, String),
+	#[serde(default)]
+	uniforms: Vec<(String, RonValue)>,
+	/// Uniforms supplied by the renderer each frame
+	#[serde(default)]
+	attributes: Vec<String>,
+	/// Opaque when left out
+	#[serde(default)]
+	blend: BlendMode,
+}
+
+/// Loads materials from RON files.
+/// Shaders are compiled once per vertex/fragment pair and shared between materials.
+pub struct MaterialManager {
+	shaders: HashMap<(String, String), Shader>,
+	materials: HashMap<String, Material>,
+}
+
+impl Default for MaterialManager {
+	fn default() -> Self {
+		MaterialManager::new()
+	}
+}
+
+impl MaterialManager {
+	pub fn new() -> Self {
+		MaterialManager {
+			shaders: HashMap::new(),
+			materials: HashMap::new(),
+		}
+	}
+
+	/// Loads a material file and stores it under its declared name.
+	pub fn load(&mut self, path: &str) -> Result<Material, MaterialError> {
+		let src = match fs::read_to_string(path) {
+			Ok(src) => src,
+			Err(e) => return Err(MaterialError::Io(path.to_owned(), e)),
+		};
+		let asset: MaterialAsset = match ron::from_str(&src) {
+			Ok(asset) => asset,
+			Err(e) => return Err(MaterialError::Parse(path.to_owned(), e)),
+		};
+
+		let shader = self.get_shader(&asset.shader)?;
+		let active = shader.active_uniforms();
+
+		for name in &asset.attributes {
+			if !active.contains_key(name) {
+				return Err(MaterialError::UnknownUniform {
+					material: asset.name.clone(),
+					uniform: name.clone(),
+				});
+			}
+		}
+
+		let mut uniforms = Vec::new();
+		for (name, value) in &asset.uniforms {
+			let type_ = match active.get(name) {
+				Some(type_) => *type_,
+				None => {
+					return Err(MaterialError::UnknownUniform {
+						material: asset.name.clone(),
+						uniform: name.clone(),
+					})
+				}
+			};
+
+			match resolve_uniform(type_, value) {
+				Ok(value) => uniforms.push((name.clone(), value)),
+				Err(expected) => {
+					return Err(MaterialError::TypeMismatch {
+						material: asset.name.clone(),
+						uniform: name.clone(),
+						expected,
+					})
+				}
+			}
+		}
+
+		let material = Material {
+			uniforms,
+			blend: asset.blend,
+			..Material::new(shader, vector!(1.0, 1.0, 1.0), 0.0, 1.0, 1.0)
+		};
+		self.materials.insert(asset.name, material.clone());
+
+		Ok(material)
+	}
+
+	pub fn get(&self, name: &str) -> Option<&Material> {
+		self.materials.get(name)
+	}
+
+	fn get_shader(&mut self, paths: &(String, String)) -> Result<Shader, MaterialError> {
+		if let Some(shader) = self.shaders.get(paths) {
+			return Ok(*shader);
+		}
+
+		for path in [&paths.0, &paths.1] {
+			if !Path::new(path).is_file() {
+				return Err(MaterialError::MissingShader(path.clone()));
+			}
+		}
+
+		let shader = match Shader::new(&paths.0, &paths.1) {
+			Ok(shader) => shader,
+			Err(e) => return Err(MaterialError::Shader(e)),
+		};
+		self.shaders.insert(paths.clone(), shader);
+
+		Ok(shader)
+	}
+}
+
+/// Converts a file value into the uniform type used by the shader.
+/// Returns the expected type name on mismatch.
+fn resolve_uniform(type_: GLenum, value: &RonValue) -> Result<UniformValue, &'static str> {
+	match (type_, value) {
+		(gl::FLOAT, RonValue::Number(v)) => Ok(UniformValue::Float(*v as f32)),
+		(gl::FLOAT, _) => Err("float"),
+		(gl::INT | gl::BOOL | gl::SAMPLER_2D, RonValue::Number(v)) if v.fract() == 0.0 => {
+			Ok(UniformValue::Int(*v as i32))
+		}
+		(gl::INT | gl::BOOL | gl::SAMPLER_2D, _) => Err("int"),
+		(gl::FLOAT_VEC3, RonValue::Tuple(v)) if v.len() == 3 => {
+			Ok(UniformValue::Vec3(Vector3::new(v[0], v[1], v[2])))
+		}
+		(gl::FLOAT_VEC3, _) => Err("vec3"),
+		_ => Err("a supported uniform type (float, int, bool, sampler2D or vec3)"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn value(src: &str) -> RonValue {
+		ron::from_str(src).unwrap()
+	}
+
+	#[test]
+	fn accepted_pairings() {
+		assert!(matches!(
+			resolve_uniform(gl::FLOAT, &value("0.5")),
+			Ok(UniformValue::Float(v)) if v == 0.5
+		));
+		// Whole numbers are fine for floats too
+		assert!(matches!(
+			resolve_uniform(gl::FLOAT, &value("2")),
+			Ok(UniformValue::Float(v)) if v == 2.0
+		));
+		for type_ in [gl::INT, gl::BOOL, gl::SAMPLER_2D] {
+			assert!(matches!(
+				resolve_uniform(type_, &value("3")),
+				Ok(UniformValue::Int(3))
+			));
+		}
+		assert!(matches!(
+			resolve_uniform(gl::FLOAT_VEC3, &value("(1.0, 0.5, 0.0)")),
+			Ok(UniformValue::Vec3(v)) if v == Vector3::new(1.0, 0.5, 0.0)
+		));
+	}
+
+	#[test]
+	fn mismatches_name_the_expected_type() {
+		let vec3 = value("(1.0, 0.5, 0.0)");
+		assert_eq!(resolve_uniform(gl::FLOAT, &vec3).unwrap_err(), "float");
+		assert_eq!(resolve_uniform(gl::INT, &vec3).unwrap_err(), "int");
+		assert_eq!(resolve_uniform(gl::BOOL, &vec3).unwrap_err(), "int");
+		// Ints don't silently truncate fractions
+		assert_eq!(
+			resolve_uniform(gl::SAMPLER_2D, &value("1.5")).unwrap_err(),
+			"int"
+		);
+		assert_eq!(
+			resolve_uniform(gl::FLOAT_VEC3, &value("1.0")).unwrap_err(),
+			"vec3"
+		);
+		assert_eq!(
+			resolve_uniform(gl::FLOAT_VEC3, &value("(1.0, 0.5)")).unwrap_err(),
+			"vec3"
+		);
+		assert_eq!(
+			resolve_uniform(gl::FLOAT_MAT4, &value("1.0")).unwrap_err(),
+			"a supported uniform type (float, int, bool, sampler2D or vec3)"
+		);
+	}
+
+	#[test]
+	fn mismatch_error_message() {
+		let error = MaterialError::TypeMismatch {
+			material: "green".to_owned(),
+			uniform: "tint".to_owned(),
+			expected: resolve_uniform(gl::FLOAT_VEC3, &value("1.0")).unwrap_err(),
+		};
+		assert_eq!(
+			error.to_string(),
+			"Material green: uniform tint expects a value of type vec3"
+		);
+	}
+}
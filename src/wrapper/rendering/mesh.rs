@@ -135,7 +135,7 @@ impl Mesh {
 			gl::EnableVertexAttribArray(2);
 			gl::VertexAttribPointer(
 				2,
-				3,
+				2,
 				gl::FLOAT,
 				gl::FALSE,
 				size,
@@ -1,6 +1,9 @@
 mod framebuffer;
+mod gltf_loader;
 mod material;
+mod material_manager;
 mod model_loader;
+mod pixelbuffer;
 mod renderbuffer;
 mod texture;
 mod uniform_manager;
@@ -9,13 +12,16 @@ mod uniformbuffer;
 pub mod mesh;
 pub mod shader;
 
+pub use gltf_loader::*;
 pub use model_loader::*;
 
 pub use material::*;
+pub use material_manager::*;
 
 pub use texture::*;
 
 pub use framebuffer::*;
+pub use pixelbuffer::*;
 pub use renderbuffer::*;
 pub use uniformbuffer::*;
 
@@ -0,0 +1,90 @@
+use gl::types::*;
+use std::ptr;
+
+/// Pixel pack buffer for reading the framebuffer back without waiting for the GPU.
+/// `read_pixels` only queues the copy, `take_f32` a frame later finds it done.
+pub struct PixelBuffer {
+	pub id: u32,
+	/// Capacity in bytes
+	pub size: usize,
+	/// Whether `read_pixels` queued a copy that wasn't taken yet
+	pending: bool,
+}
+
+impl PixelBuffer {
+	pub fn new(size: usize) -> PixelBuffer {
+		unsafe {
+			let mut id = 0;
+			gl::GenBuffers(1, &mut id);
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, id);
+			gl::BufferData(
+				gl::PIXEL_PACK_BUFFER,
+				size as isize,
+				ptr::null(),
+				gl::STREAM_READ,
+			);
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+
+			PixelBuffer {
+				id,
+				size,
+				pending: false,
+			}
+		}
+	}
+
+	/// Queues a copy of the bound read framebuffer into the buffer.
+	/// The pixels must fit into `size` bytes.
+	pub fn read_pixels(&mut self, width: u32, height: u32, format: GLenum, type_: GLenum) {
+		unsafe {
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.id);
+			gl::ReadPixels(
+				0,
+				0,
+				width as i32,
+				height as i32,
+				format,
+				type_,
+				ptr::null_mut(),
+			);
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+		}
+		self.pending = true;
+	}
+
+	/// Copies out the pixels of the last `read_pixels` as floats,
+	/// None if nothing was queued since the last take.
+	pub fn take_f32(&mut self) -> Option<Vec<f32>> {
+		if !self.pending {
+			return None;
+		}
+		self.pending = false;
+
+		let mut data = vec![0.0f32; self.size / 4];
+		unsafe {
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.id);
+			let mapped = gl::MapBufferRange(
+				gl::PIXEL_PACK_BUFFER,
+				0,
+				self.size as isize,
+				gl::MAP_READ_BIT,
+			);
+			if !mapped.is_null() {
+				ptr::copy_nonoverlapping(mapped as *const f32, data.as_mut_ptr(), data.len());
+				gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+			}
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+
+			match mapped.is_null() {
+				true => None,
+				false => Some(data),
+			}
+		}
+	}
+
+	pub fn delete(&self) {
+		unsafe {
+			gl::DeleteBuffers(1, &self.id);
+		}
+	}
+}
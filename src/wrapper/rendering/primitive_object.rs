@@ -78,6 +78,16 @@ impl Primitive for Quad {
 	}
 }
 
+impl Quad {
+	/// Frees the vertex array and buffer, the quad can't be drawn afterwards.
+	pub fn delete(&self) {
+		unsafe {
+			gl::DeleteVertexArrays(1, &self.vao);
+			gl::DeleteBuffers(1, &self.vbo);
+		}
+	}
+}
+
 impl Primitive for Cube {
 	fn new() -> Cube {
 		unsafe {
@@ -89,17 +99,17 @@ impl Primitive for Cube {
 			gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
 			gl::BufferData(
 				gl::ARRAY_BUFFER,
-				(size_of::<f32>() * 24) as isize,
+				(size_of::<f32>() * 108) as isize,
 				cube_vertices.as_ptr() as *const c_void,
 				gl::STATIC_DRAW,
 			);
 			gl::EnableVertexAttribArray(0);
 			gl::VertexAttribPointer(
 				0,
-				2,
+				3,
 				gl::FLOAT,
 				gl::FALSE,
-				(size_of::<f32>() * 8) as i32,
+				(size_of::<f32>() * 3) as i32,
 				0 as *const c_void,
 			);
 
@@ -115,3 +125,13 @@ impl Primitive for Cube {
 		}
 	}
 }
+
+impl Cube {
+	/// Frees the vertex array and buffer, the cube can't be drawn afterwards.
+	pub fn delete(&self) {
+		unsafe {
+			gl::DeleteVertexArrays(1, &self.vao);
+			gl::DeleteBuffers(1, &self.vbo);
+		}
+	}
+}
@@ -1,21 +1,24 @@
 pub struct RenderBuffer {
 	rbo: u32,
+	pub width: u32,
+	pub height: u32,
 }
 
 impl RenderBuffer {
+	/// Creates a depth buffer and attaches it to the bound framebuffer.
+	/// Give it to `FrameBuffer::set_depth_buffer` so it is resized and freed with it.
 	pub fn new(width: u32, height: u32) -> Self {
-		let mut buf = RenderBuffer { rbo: 0 };
+		let mut buf = RenderBuffer {
+			rbo: 0,
+			width,
+			height,
+		};
 
 		unsafe {
 			let mut rbo: u32 = 0;
 			gl::GenRenderbuffers(1, &mut rbo);
-			gl::BindRenderbuffer(gl::RENDERBUFFER, rbo);
-			gl::RenderbufferStorage(
-				gl::RENDERBUFFER,
-				gl::DEPTH_COMPONENT,
-				width as i32,
-				height as i32,
-			);
+			buf.rbo = rbo;
+			buf.allocate();
 
 			gl::FramebufferRenderbuffer(
 				gl::FRAMEBUFFER,
@@ -23,10 +26,33 @@ impl RenderBuffer {
 				gl::RENDERBUFFER,
 				rbo,
 			);
-
-			buf.rbo = rbo;
 		}
 
 		buf
 	}
+
+	/// Reallocates the storage, the contents are undefined afterwards.
+	pub fn resize(&mut self, width: u32, height: u32) {
+		self.width = width;
+		self.height = height;
+		self.allocate();
+	}
+
+	pub fn delete(&self) {
+		unsafe {
+			gl::DeleteRenderbuffers(1, &self.rbo);
+		}
+	}
+
+	fn allocate(&self) {
+		unsafe {
+			gl::BindRenderbuffer(gl::RENDERBUFFER, self.rbo);
+			gl::RenderbufferStorage(
+				gl::RENDERBUFFER,
+				gl::DEPTH_COMPONENT,
+				self.width as i32,
+				self.height as i32,
+			);
+		}
+	}
 }
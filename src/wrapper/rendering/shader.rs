@@ -1,13 +1,11 @@
 use crate::util::{create_whitespace_cstring_with_len, load_to_string, to_cstring};
 use crate::wrapper::error::ShaderError;
+use gl::types::*;
 use nalgebra::{Matrix4, Vector3};
-use std::{ffi::CString, ptr, str};
+use std::{collections::HashMap, ffi::CString, path::Path, ptr, str};
 
-enum ShaderType {
-	VERTEX,
-	FRAGMENT,
-	PROGRAM,
-}
+/// Limit for `#include` chains, catches files including each other.
+const MAX_INCLUDE_DEPTH: u32 = 8;
 
 #[derive(Copy)]
 pub struct Shader {
@@ -27,8 +25,8 @@ impl Shader {
 	pub fn new(v_src_path: &str, f_src_path: &str) -> Result<Shader, ShaderError> {
 		let mut shader = Shader { id: 0 };
 
-		let vertex_src = to_cstring(load_to_string(v_src_path).unwrap()).unwrap();
-		let fragment_src = to_cstring(load_to_string(f_src_path).unwrap()).unwrap();
+		let vertex_src = Shader::load_source(v_src_path)?;
+		let fragment_src = Shader::load_source(f_src_path)?;
 
 		unsafe {
 			let vertex = gl::CreateShader(gl::VERTEX_SHADER);
@@ -73,6 +71,83 @@ impl Shader {
 		Ok(shader)
 	}
 
+	fn load_source(path: &str) -> Result<CString, ShaderError> {
+		let src = Shader::expand_includes(path, 0)?;
+
+		match to_cstring(src) {
+			Ok(src) => Ok(src),
+			Err(e) => Err(format!("{}: {}", path, e)),
+		}
+	}
+
+	/// Reads a shader file, replacing `#include "file"` lines with the
+	/// contents of that file. Paths are relative to the including file.
+	fn expand_includes(path: &str, depth: u32) -> Result<String, ShaderError> {
+		if depth > MAX_INCLUDE_DEPTH {
+			return Err(format!("{}: includes nested too deep", path));
+		}
+
+		let src = match load_to_string(path) {
+			Ok(src) => src,
+			Err(e) => return Err(format!("{}: {}", path, e)),
+		};
+
+		let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+		let mut expanded = String::with_capacity(src.len());
+		for line in src.lines() {
+			match line.trim().strip_prefix("#include") {
+				Some(name) => {
+					let name = name.trim().trim_matches('"');
+					let include = dir.join(name);
+					expanded.push_str(&Shader::expand_includes(
+						&include.to_string_lossy(),
+						depth + 1,
+					)?);
+				}
+				None => expanded.push_str(line),
+			}
+			expanded.push('\n');
+		}
+
+		Ok(expanded)
+	}
+
+	/// Returns name and GL type of every active uniform in the program.
+	/// Array uniforms are listed without the `[0]` suffix.
+	pub fn active_uniforms(&self) -> HashMap<String, GLenum> {
+		let mut uniforms = HashMap::new();
+
+		unsafe {
+			let mut count: GLint = 0;
+			gl::GetProgramiv(self.id, gl::ACTIVE_UNIFORMS, &mut count);
+			let mut max_len: GLint = 0;
+			gl::GetProgramiv(self.id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_len);
+
+			for i in 0..count as GLuint {
+				let mut len: GLsizei = 0;
+				let mut size: GLint = 0;
+				let mut type_: GLenum = 0;
+				let mut name = vec![0u8; max_len as usize + 1];
+				gl::GetActiveUniform(
+					self.id,
+					i,
+					name.len() as GLsizei,
+					&mut len,
+					&mut size,
+					&mut type_,
+					name.as_mut_ptr() as *mut GLchar,
+				);
+				name.truncate(len as usize);
+
+				let name = String::from_utf8_lossy(&name);
+				let name = name.trim_end_matches("[0]").to_owned();
+				uniforms.insert(name, type_);
+			}
+		}
+
+		uniforms
+	}
+
 	/// Activates shader program
 	pub fn use_program(&self) {
 		unsafe {
@@ -146,6 +221,13 @@ impl Shader {
 		}
 	}
 
+	pub fn set_vec2(&self, name: &str, x: f32, y: f32) {
+		unsafe {
+			let _name = &CString::new(name).expect("Unable to convert string to CString");
+			gl::Uniform2f(gl::GetUniformLocation(self.id, _name.as_ptr()), x, y);
+		}
+	}
+
 	pub fn set_vec3(&self, name: &str, x: f32, y: f32, z: f32) {
 		unsafe {
 			let _name = &CString::new(name).expect("Unable to convert string to CString");
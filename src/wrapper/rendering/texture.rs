@@ -2,7 +2,10 @@ use crate::util::to_cstring;
 use crate::wrapper::render::core::shader::Shader;
 use gl::types::*;
 use image;
+use image::codecs::hdr::HdrDecoder;
 use image::DynamicImage::*;
+use std::fs::File;
+use std::io::BufReader;
 use std::os::raw::c_void;
 use std::path::Path;
 
@@ -21,6 +24,11 @@ pub struct Texture {
 	pub path: String,
 
 	pub index: u32,
+	/// TEXTURE_2D, TEXTURE_2D_ARRAY, TEXTURE_3D or TEXTURE_CUBE_MAP
+	pub target: GLenum,
+	/// Pixel format and type the texture was created with, reused by `resize`
+	pub format: GLenum,
+	pub type_: GLenum,
 }
 
 impl Texture {
@@ -39,7 +47,7 @@ impl Texture {
 				options.height as i32,
 				0,
 				options.format,
-				gl::UNSIGNED_BYTE,
+				options.type_,
 				data,
 			);
 			return buf;
@@ -57,17 +65,89 @@ impl Texture {
 			_ => 0,
 		};
 
-		let data = img.as_bytes();
+		let options = TextureOptions {
+			width: img.width(),
+			height: img.height(),
+			internal_format: format,
+			format,
+			type_: gl::UNSIGNED_BYTE,
+		};
+
+		let mut texture = Texture::from_data(type_name, &options, img.as_bytes())
+			.expect("Texture failed to load");
+		texture.path = path.to_owned();
+
+		texture
+	}
+
+	/// Loads a Radiance .hdr file without clamping it to 8 bits.
+	/// The texture is clamped and not mipmapped, for equirectangular environment maps.
+	pub fn from_hdr_file(type_name: &str, path: &str) -> Result<Texture, image::ImageError> {
+		let file = File::open(Path::new(path)).map_err(image::ImageError::IoError)?;
+		let decoder = HdrDecoder::new(BufReader::new(file))?;
+		let meta = decoder.metadata();
+		let pixels = decoder.read_image_hdr()?;
+
+		// Flip rows so the first row is the bottom of the image
+		let mut data: Vec<f32> = Vec::with_capacity(pixels.len() * 3);
+		for row in pixels.chunks(meta.width as usize).rev() {
+			for pixel in row {
+				data.extend_from_slice(&pixel.0);
+			}
+		}
+
+		let options = TextureOptions {
+			width: meta.width,
+			height: meta.height,
+			internal_format: gl::RGB16F,
+			format: gl::RGB,
+			type_: gl::FLOAT,
+		};
 
+		let mut texture = Texture::clamped(type_name, &options);
+		texture.set_image(gl::TEXTURE_2D, 0, &options, &data);
+		texture.path = path.to_owned();
+
+		Ok(texture)
+	}
+
+	/// Creates an empty texture with linear filtering that is clamped to its edges.
+	/// Attach it with `FrameBuffer::attach_color_texture` to render into it.
+	pub fn clamped(type_name: &str, options: &TextureOptions) -> Texture {
 		let texture_id = unsafe {
-			let options = TextureOptions {
-				width: img.width(),
-				height: img.height(),
-				internal_format: format,
-				format,
-				type_: gl::UNSIGNED_BYTE,
-			};
-			let buf = Texture::create_buffer(&options, &data[0] as *const u8 as *const c_void);
+			let buf = Texture::create_buffer(options, std::ptr::null());
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+
+			buf
+		};
+
+		Texture {
+			id: texture_id,
+			type_name: type_name.to_owned(),
+			path: "".to_owned(),
+			index: 0,
+			target: gl::TEXTURE_2D,
+			format: options.format,
+			type_: options.type_,
+		}
+	}
+
+	/// Creates a mipmapped, repeating texture from raw pixel data.
+	/// Fails on empty data, there is nothing to upload.
+	pub fn from_data(
+		type_name: &str,
+		options: &TextureOptions,
+		data: &[u8],
+	) -> Result<Texture, String> {
+		if data.is_empty() {
+			return Err(format!("{} has no pixel data", type_name));
+		}
+
+		let texture_id = unsafe {
+			let buf = Texture::create_buffer(options, data.as_ptr() as *const c_void);
 			gl::GenerateMipmap(gl::TEXTURE_2D);
 			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
 			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
@@ -81,14 +161,15 @@ impl Texture {
 			buf
 		};
 
-		let texture = Texture {
+		Ok(Texture {
 			id: texture_id,
 			type_name: type_name.to_owned(),
-			path: path.to_owned(),
+			path: "".to_owned(),
 			index: 0,
-		};
-
-		texture
+			target: gl::TEXTURE_2D,
+			format: options.format,
+			type_: options.type_,
+		})
 	}
 
 	pub fn for_framebuffer(type_name: &str, index: u32, options: &TextureOptions) -> Texture {
@@ -112,6 +193,227 @@ impl Texture {
 			type_name: type_name.to_owned(),
 			path: "".to_owned(),
 			index,
+			target: gl::TEXTURE_2D,
+			format: options.format,
+			type_: options.type_,
+		}
+	}
+
+	/// Creates a depth texture and attaches it to the bound framebuffer.
+	/// Samples outside the texture read as max depth.
+	pub fn depth_for_framebuffer(type_name: &str, options: &TextureOptions) -> Texture {
+		let texture_id = unsafe {
+			let buf = Texture::create_buffer(options, std::ptr::null());
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+			gl::TexParameteri(
+				gl::TEXTURE_2D,
+				gl::TEXTURE_WRAP_S,
+				gl::CLAMP_TO_BORDER as i32,
+			);
+			gl::TexParameteri(
+				gl::TEXTURE_2D,
+				gl::TEXTURE_WRAP_T,
+				gl::CLAMP_TO_BORDER as i32,
+			);
+			let border = [1.0f32, 1.0, 1.0, 1.0];
+			gl::TexParameterfv(gl::TEXTURE_2D, gl::TEXTURE_BORDER_COLOR, border.as_ptr());
+			gl::FramebufferTexture2D(
+				gl::FRAMEBUFFER,
+				gl::DEPTH_ATTACHMENT,
+				gl::TEXTURE_2D,
+				buf,
+				0,
+			);
+
+			buf
+		};
+
+		Texture {
+			id: texture_id,
+			type_name: type_name.to_owned(),
+			path: "".to_owned(),
+			index: 0,
+			target: gl::TEXTURE_2D,
+			format: options.format,
+			type_: options.type_,
+		}
+	}
+
+	/// Creates a layered depth texture for rendering one layer at a time.
+	/// Layers are attached with `FrameBuffer::attach_depth_layer`.
+	pub fn depth_array(type_name: &str, options: &TextureOptions, layers: u32) -> Texture {
+		let texture_id = unsafe {
+			let mut buf: u32 = 0;
+			gl::GenTextures(1, &mut buf);
+			gl::BindTexture(gl::TEXTURE_2D_ARRAY, buf);
+			gl::TexImage3D(
+				gl::TEXTURE_2D_ARRAY,
+				0,
+				options.internal_format as i32,
+				options.width as i32,
+				options.height as i32,
+				layers as i32,
+				0,
+				options.format,
+				options.type_,
+				std::ptr::null(),
+			);
+			let target = gl::TEXTURE_2D_ARRAY;
+			gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+			gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+			gl::TexParameteri(target, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as i32);
+			gl::TexParameteri(target, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as i32);
+			let border = [1.0f32, 1.0, 1.0, 1.0];
+			gl::TexParameterfv(target, gl::TEXTURE_BORDER_COLOR, border.as_ptr());
+
+			buf
+		};
+
+		Texture {
+			id: texture_id,
+			type_name: type_name.to_owned(),
+			path: "".to_owned(),
+			index: 0,
+			target: gl::TEXTURE_2D_ARRAY,
+			format: options.format,
+			type_: options.type_,
+		}
+	}
+
+	/// Creates a depth cube map, faces are attached with
+	/// `FrameBuffer::attach_depth_cube_face`.
+	pub fn depth_cube(type_name: &str, options: &TextureOptions) -> Texture {
+		let texture_id = unsafe {
+			let mut buf: u32 = 0;
+			gl::GenTextures(1, &mut buf);
+			gl::BindTexture(gl::TEXTURE_CUBE_MAP, buf);
+			for face in 0..6 {
+				gl::TexImage2D(
+					gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+					0,
+					options.internal_format as i32,
+					options.width as i32,
+					options.height as i32,
+					0,
+					options.format,
+					options.type_,
+					std::ptr::null(),
+				);
+			}
+			Texture::cube_parameters(gl::NEAREST, gl::NEAREST);
+
+			buf
+		};
+
+		Texture {
+			id: texture_id,
+			type_name: type_name.to_owned(),
+			path: "".to_owned(),
+			index: 0,
+			target: gl::TEXTURE_CUBE_MAP,
+			format: options.format,
+			type_: options.type_,
+		}
+	}
+
+	/// Creates a color cube map with `mip_levels` levels allocated for every face.
+	/// Faces are attached with `FrameBuffer::attach_color_cube_face`.
+	pub fn cube(type_name: &str, options: &TextureOptions, mip_levels: u32) -> Texture {
+		let texture_id = unsafe {
+			let mut buf: u32 = 0;
+			gl::GenTextures(1, &mut buf);
+			gl::BindTexture(gl::TEXTURE_CUBE_MAP, buf);
+			for mip in 0..mip_levels {
+				for face in 0..6 {
+					gl::TexImage2D(
+						gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+						mip as i32,
+						options.internal_format as i32,
+						(options.width >> mip).max(1) as i32,
+						(options.height >> mip).max(1) as i32,
+						0,
+						options.format,
+						options.type_,
+						std::ptr::null(),
+					);
+				}
+			}
+			gl::TexParameteri(
+				gl::TEXTURE_CUBE_MAP,
+				gl::TEXTURE_MAX_LEVEL,
+				mip_levels as i32 - 1,
+			);
+
+			let min_filter = match mip_levels {
+				1 => gl::LINEAR,
+				_ => gl::LINEAR_MIPMAP_LINEAR,
+			};
+			Texture::cube_parameters(min_filter, gl::LINEAR);
+
+			buf
+		};
+
+		Texture {
+			id: texture_id,
+			type_name: type_name.to_owned(),
+			path: "".to_owned(),
+			index: 0,
+			target: gl::TEXTURE_CUBE_MAP,
+			format: options.format,
+			type_: options.type_,
+		}
+	}
+
+	/// Creates a clamped, linearly filtered 3D texture from RGB float data,
+	/// with red changing fastest. Used as color lookup table.
+	pub fn lut_3d(type_name: &str, size: u32, data: &[f32]) -> Texture {
+		let texture_id = unsafe {
+			let mut buf: u32 = 0;
+			gl::GenTextures(1, &mut buf);
+			gl::BindTexture(gl::TEXTURE_3D, buf);
+			gl::TexImage3D(
+				gl::TEXTURE_3D,
+				0,
+				gl::RGB16F as i32,
+				size as i32,
+				size as i32,
+				size as i32,
+				0,
+				gl::RGB,
+				gl::FLOAT,
+				data.as_ptr() as *const c_void,
+			);
+			let target = gl::TEXTURE_3D;
+			gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+			gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+			gl::TexParameteri(target, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+			gl::TexParameteri(target, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+			gl::TexParameteri(target, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as i32);
+
+			buf
+		};
+
+		Texture {
+			id: texture_id,
+			type_name: type_name.to_owned(),
+			path: "".to_owned(),
+			index: 0,
+			target: gl::TEXTURE_3D,
+			format: gl::RGB,
+			type_: gl::FLOAT,
+		}
+	}
+
+	/// Sets filtering and clamps all three axes of the bound cube map.
+	fn cube_parameters(min_filter: GLenum, mag_filter: GLenum) {
+		unsafe {
+			let target = gl::TEXTURE_CUBE_MAP;
+			gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+			gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, mag_filter as i32);
+			gl::TexParameteri(target, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+			gl::TexParameteri(target, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+			gl::TexParameteri(target, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as i32);
 		}
 	}
 
@@ -140,6 +442,9 @@ impl Texture {
 			type_name: type_name.to_owned(),
 			path: "".to_owned(),
 			index: 0,
+			target: gl::TEXTURE_2D,
+			format: options.format,
+			type_: options.type_,
 		}
 	}
 }
@@ -153,7 +458,123 @@ impl Texture {
 				index as i32,
 			);
 			gl::ActiveTexture(gl::TEXTURE0 + index);
+			gl::BindTexture(self.target, self.id);
+		}
+	}
+
+	/// Binds texture to a texture unit without touching shader uniforms.
+	pub fn bind_unit(&self, unit: u32) {
+		unsafe {
+			gl::ActiveTexture(gl::TEXTURE0 + unit);
+			gl::BindTexture(self.target, self.id);
+		}
+	}
+
+	/// Replaces one image of the texture with float pixel data.
+	/// `image_target` is TEXTURE_2D or one of the TEXTURE_CUBE_MAP faces,
+	/// `options` gives the size of that mip level.
+	pub fn set_image(
+		&self,
+		image_target: GLenum,
+		mip: u32,
+		options: &TextureOptions,
+		data: &[f32],
+	) {
+		unsafe {
+			gl::BindTexture(self.target, self.id);
+			gl::TexImage2D(
+				image_target,
+				mip as i32,
+				options.internal_format as i32,
+				options.width as i32,
+				options.height as i32,
+				0,
+				options.format,
+				gl::FLOAT,
+				data.as_ptr() as *const c_void,
+			);
+		}
+	}
+
+	/// Reads one image of the texture back as floats.
+	/// `components` is the number of channels in `format`.
+	pub fn read_image(
+		&self,
+		image_target: GLenum,
+		mip: u32,
+		format: GLenum,
+		components: usize,
+	) -> Vec<f32> {
+		unsafe {
+			gl::BindTexture(self.target, self.id);
+
+			let (mut width, mut height) = (0, 0);
+			gl::GetTexLevelParameteriv(image_target, mip as i32, gl::TEXTURE_WIDTH, &mut width);
+			gl::GetTexLevelParameteriv(image_target, mip as i32, gl::TEXTURE_HEIGHT, &mut height);
+
+			let mut data = vec![0.0f32; width as usize * height as usize * components];
+			gl::GetTexImage(
+				image_target,
+				mip as i32,
+				format,
+				gl::FLOAT,
+				data.as_mut_ptr() as *mut c_void,
+			);
+
+			data
+		}
+	}
+
+	/// Reallocates a 2D texture with a new size, keeping its internal format.
+	/// The contents are undefined afterwards and only the first mip level is kept.
+	/// Attachments of framebuffers stay valid.
+	pub fn resize(&self, width: u32, height: u32) {
+		unsafe {
 			gl::BindTexture(gl::TEXTURE_2D, self.id);
+			let mut internal_format = 0;
+			gl::GetTexLevelParameteriv(
+				gl::TEXTURE_2D,
+				0,
+				gl::TEXTURE_INTERNAL_FORMAT,
+				&mut internal_format,
+			);
+
+			gl::TexImage2D(
+				gl::TEXTURE_2D,
+				0,
+				internal_format,
+				width as i32,
+				height as i32,
+				0,
+				self.format,
+				self.type_,
+				std::ptr::null(),
+			);
+		}
+	}
+
+	/// Sets min/mag filtering and wrapping on the S and T axes.
+	pub fn set_parameters(&self, filter: GLenum, wrap: GLenum) {
+		unsafe {
+			gl::BindTexture(self.target, self.id);
+			gl::TexParameteri(self.target, gl::TEXTURE_MIN_FILTER, filter as i32);
+			gl::TexParameteri(self.target, gl::TEXTURE_MAG_FILTER, filter as i32);
+			gl::TexParameteri(self.target, gl::TEXTURE_WRAP_S, wrap as i32);
+			gl::TexParameteri(self.target, gl::TEXTURE_WRAP_T, wrap as i32);
+		}
+	}
+
+	pub fn generate_mipmap(&self) {
+		unsafe {
+			gl::BindTexture(self.target, self.id);
+			gl::GenerateMipmap(self.target);
+		}
+	}
+
+	/// Frees the GL texture, clones of this texture become invalid.
+	pub fn delete(&self) {
+		unsafe {
+			gl::DeleteTextures(1, &self.id);
 		}
 	}
 
@@ -161,7 +582,7 @@ impl Texture {
 	pub fn activate(&self) {
 		unsafe {
 			gl::ActiveTexture(gl::TEXTURE0 + self.index);
-			gl::BindTexture(gl::TEXTURE_2D, self.id);
+			gl::BindTexture(self.target, self.id);
 		}
 	}
 }
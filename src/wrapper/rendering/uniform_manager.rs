@@ -27,6 +27,12 @@ pub struct UniformManager {
 	uniforms: HashMap<String, Box<dyn Valid>>,
 }
 
+impl Default for UniformManager {
+	fn default() -> Self {
+		UniformManager::new()
+	}
+}
+
 impl UniformManager {
 	pub fn new() -> Self {
 		UniformManager {
@@ -71,4 +71,18 @@ impl UniformBuffer {
 			gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
 		}
 	}
+
+	/// Writes a slice of plain data starting at `offset`.
+	pub fn set_data<T>(&self, offset: usize, data: &[T]) {
+		unsafe {
+			gl::BindBuffer(gl::UNIFORM_BUFFER, self.id);
+			gl::BufferSubData(
+				gl::UNIFORM_BUFFER,
+				offset as GLintptr,
+				std::mem::size_of_val(data) as GLsizeiptr,
+				data.as_ptr() as *const c_void,
+			);
+			gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+		}
+	}
 }
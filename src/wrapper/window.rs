@@ -1,47 +1,174 @@
 use glfw::Context;
 use std::sync::mpsc::Receiver;
 
-use super::frame::Frame;
+use super::frame::{DepthRange, Frame, Viewport};
+
+/// How the window covers the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowMode {
+	Windowed,
+	/// Covers the monitor without changing its video mode, switches quickly
+	Borderless,
+	/// Takes over the monitor with the video mode closest to `WindowSettings::video_mode`
+	Fullscreen,
+}
+
+/// Resolution and refresh rate of a monitor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VideoMode {
+	pub width: u32,
+	pub height: u32,
+	/// Highest available when None
+	pub refresh_rate: Option<u32>,
+}
+
+/// Frames the driver waits for before showing a new image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VSync {
+	Off,
+	/// Every vertical blank, the same as `Interval(1)`
+	On,
+	/// Like `On`, but late frames are shown right away when the driver supports it
+	Adaptive,
+	/// Every n-th vertical blank
+	Interval(u32),
+}
+
+impl VSync {
+	fn swap_interval(&self) -> glfw::SwapInterval {
+		match *self {
+			VSync::Off => glfw::SwapInterval::None,
+			VSync::On => glfw::SwapInterval::Sync(1),
+			VSync::Adaptive => glfw::SwapInterval::Adaptive,
+			VSync::Interval(n) => glfw::SwapInterval::Sync(n),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlProfile {
+	Core,
+	Compatibility,
+}
 
 /// Settings for window object
+/// The context settings only apply when the window is created,
+/// `Window` has setters for the others.
+#[derive(Clone, Debug)]
 pub struct WindowSettings {
+	/// Size of the window while windowed
 	pub width: u32,
 	pub height: u32,
 	pub title: String,
+
+	pub mode: WindowMode,
+	/// Index into `Window::monitors`, the primary monitor when None or out of range
+	pub monitor: Option<usize>,
+	/// Video mode for `WindowMode::Fullscreen`, `width` and `height` when None
+	pub video_mode: Option<VideoMode>,
+	pub vsync: VSync,
+	/// Whether the user can resize the window
+	pub resizable: bool,
+	/// Image file shown in the title bar and task bar.
+	/// Applied by `WindowPlugin`, or with `Window::set_icon` which reports load errors.
+	pub icon: Option<String>,
+
+	/// Requested OpenGL version, drivers may provide a newer compatible one
+	pub gl_version: (u32, u32),
+	pub gl_profile: GlProfile,
+	/// Needed for `Window::debug_message_callback` on most drivers
+	pub debug_context: bool,
+	/// MSAA samples of the default framebuffer, 0 disables it.
+	/// The scene is rendered into single sampled targets and copied to the window,
+	/// so this only smooths what is drawn straight to the window, like overlays.
+	pub samples: u32,
 }
 
 impl Default for WindowSettings {
-	/// 800, 800
+	/// 800, 800 windowed with vsync and an OpenGL 3.3 core context,
+	/// which is a debug context in debug builds
 	fn default() -> WindowSettings {
 		WindowSettings {
 			width: 800,
 			height: 800,
 			title: "Window".to_string(),
+			mode: WindowMode::Windowed,
+			monitor: None,
+			video_mode: None,
+			vsync: VSync::On,
+			resizable: true,
+			icon: None,
+			gl_version: (3, 3),
+			gl_profile: GlProfile::Core,
+			debug_context: cfg!(debug_assertions),
+			samples: 0,
 		}
 	}
 }
 
-#[derive(Default)]
+#[derive(Clone)]
 pub struct Time {
+	/// Measured seconds since start at the last frame
 	pub last_frame: f64,
+	/// Seconds of game time since the last frame,
+	/// scaled by `time_scale` and 0 while paused
 	pub delta_time: f32,
+	/// Measured seconds since the last frame
+	pub real_delta_time: f32,
+	/// Seconds of game time since start
+	pub elapsed: f64,
 	pub frame_count: i32,
+	/// Frames per second over the last second
 	pub fps: f32,
 
-	last_time: f32,
+	/// Speed of game time, 0.5 is slow motion
+	pub time_scale: f32,
+	pub paused: bool,
+
+	fps_time: f32,
 	frames_per_second: i32,
 }
 
+impl Default for Time {
+	fn default() -> Time {
+		Time {
+			last_frame: 0.0,
+			delta_time: 0.0,
+			real_delta_time: 0.0,
+			elapsed: 0.0,
+			frame_count: 0,
+			fps: 0.0,
+			time_scale: 1.0,
+			paused: false,
+			fps_time: 0.0,
+			frames_per_second: 0,
+		}
+	}
+}
+
 impl Time {
+	/// Advances to the clock reading `time` in seconds.
 	pub fn update(&mut self, time: f64) {
-		self.delta_time = (time - self.last_frame) as f32;
+		self.advance((time - self.last_frame) as f32);
 		self.last_frame = time;
+	}
+
+	/// Advances by `real_delta` measured seconds without reading a clock.
+	pub fn advance(&mut self, real_delta: f32) {
+		self.last_frame += real_delta as f64;
+		self.real_delta_time = real_delta;
+		self.delta_time = match self.paused {
+			true => 0.0,
+			false => real_delta * self.time_scale,
+		};
+		self.elapsed += self.delta_time as f64;
 		self.frame_count += 1;
 
 		self.frames_per_second += 1;
-		if (time as f32 - self.last_time) > 1.0 {
-			self.last_time = time as f32;
-			self.fps = 1000.0 / self.frames_per_second as f32;
+		self.fps_time += real_delta;
+		if self.fps_time >= 1.0 {
+			self.fps = self.frames_per_second as f32 / self.fps_time;
+			self.fps_time = 0.0;
 			self.frames_per_second = 0;
 		}
 	}
@@ -54,12 +181,33 @@ pub struct Window {
 	internal_window: glfw::Window,
 	glfw: glfw::Glfw,
 	events: Receiver<(f64, glfw::WindowEvent)>,
+	/// Position and size to return to when leaving fullscreen
+	windowed_rect: (i32, i32, u32, u32),
 }
 
 impl Window {
 	pub fn new(settings: WindowSettings) -> Window {
 		let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
 
+		// Hints only affect windows created after them
+		let (major, minor) = settings.gl_version;
+		glfw.window_hint(glfw::WindowHint::ContextVersion(major, minor));
+		let profile = match settings.gl_profile {
+			GlProfile::Core => glfw::OpenGlProfileHint::Core,
+			GlProfile::Compatibility => glfw::OpenGlProfileHint::Compat,
+		};
+		// Profiles only exist from 3.2 on, earlier versions fail with anything but Any
+		if (major, minor) >= (3, 2) {
+			glfw.window_hint(glfw::WindowHint::OpenGlProfile(profile));
+		}
+		// macOS only provides core contexts that are forward compatible
+		if cfg!(target_os = "macos") && settings.gl_profile == GlProfile::Core {
+			glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
+		}
+		glfw.window_hint(glfw::WindowHint::OpenGlDebugContext(settings.debug_context));
+		glfw.window_hint(glfw::WindowHint::Samples(Some(settings.samples)));
+		glfw.window_hint(glfw::WindowHint::Resizable(settings.resizable));
+
 		// Create a windowed mode window and its OpenGL context
 		let (window, events) = glfw
 			.create_window(
@@ -70,27 +218,43 @@ impl Window {
 			)
 			.expect("Failed to create GLFW window.");
 
-		// Provides highest available version
-		glfw.window_hint(glfw::WindowHint::ContextVersion(1, 0));
-		glfw.window_hint(glfw::WindowHint::OpenGlProfile(
-			glfw::OpenGlProfileHint::Core,
-		));
+		let (x, y) = window.get_pos();
+		let windowed_rect = (x, y, settings.width, settings.height);
+		let mode = settings.mode;
 
-		Window {
+		let mut window = Window {
 			settings,
 			glfw,
 			events,
 			internal_window: window,
 			time: Time::default(),
+			windowed_rect,
+		};
+
+		if mode != WindowMode::Windowed {
+			window.settings.mode = WindowMode::Windowed;
+			window.set_mode(mode);
 		}
+
+		window
 	}
 
 	pub fn init(mut self) -> Window {
 		self.internal_window.make_current();
 		self.internal_window.set_key_polling(true);
+		self.internal_window.set_char_polling(true);
+		self.internal_window.set_mouse_button_polling(true);
+		self.internal_window.set_cursor_pos_polling(true);
+		self.internal_window.set_scroll_polling(true);
+		self.internal_window.set_focus_polling(true);
+		self.internal_window.set_size_polling(true);
 		self.internal_window.set_framebuffer_size_polling(true);
 
 		gl::load_with(|symbol| self.internal_window.get_proc_address(symbol) as *const _);
+		DepthRange::detect_support();
+		// Needs the current context
+		self.glfw
+			.set_swap_interval(self.settings.vsync.swap_interval());
 
 		return self;
 	}
@@ -107,6 +271,15 @@ impl Window {
 		return self.settings.width as f32 / self.settings.height as f32;
 	}
 
+	/// Current framebuffer size, which can differ from the window size on high DPI screens.
+	pub fn get_viewport(&self) -> Viewport {
+		let (width, height) = self.internal_window.get_framebuffer_size();
+		Viewport {
+			width: width.max(0) as u32,
+			height: height.max(0) as u32,
+		}
+	}
+
 	pub fn pre_loop(&mut self) {
 		self.time.update(self.glfw.get_time());
 	}
@@ -120,6 +293,150 @@ impl Window {
 		return &self.time;
 	}
 
+	/// Handle to the GLFW library, for joysticks and monitors.
+	pub fn glfw(&self) -> glfw::Glfw {
+		self.glfw.clone()
+	}
+
+	/// Events received by the last `post_loop`, each can only be read once.
+	pub fn events(&self) -> impl Iterator<Item = glfw::WindowEvent> + '_ {
+		glfw::flush_messages(&self.events).map(|(_, event)| event)
+	}
+
+	/// Keeps `settings` on the window size and the GL viewport on the framebuffer size.
+	/// Call with every event before other handlers.
+	pub fn handle_event(&mut self, event: &glfw::WindowEvent) {
+		match *event {
+			// Fullscreen sizes aren't kept, leaving fullscreen restores the windowed size
+			glfw::WindowEvent::Size(width, height)
+				if self.settings.mode == WindowMode::Windowed =>
+			{
+				self.settings.width = width.max(0) as u32;
+				self.settings.height = height.max(0) as u32;
+			}
+			glfw::WindowEvent::FramebufferSize(width, height) => unsafe {
+				gl::Viewport(0, 0, width, height);
+			},
+			_ => {}
+		}
+	}
+
+	/// Names of the connected monitors, the primary one first.
+	pub fn monitors(&mut self) -> Vec<String> {
+		self.glfw.with_connected_monitors(|_, monitors| {
+			monitors
+				.iter()
+				.map(|monitor| monitor.get_name().unwrap_or_default())
+				.collect()
+		})
+	}
+
+	/// Video modes of the monitor in `settings.monitor`, from the smallest up.
+	pub fn video_modes(&mut self) -> Vec<VideoMode> {
+		let index = self.settings.monitor;
+		self.glfw
+			.with_connected_monitors(|_, monitors| match select_monitor(monitors, index) {
+				Some(monitor) => monitor
+					.get_video_modes()
+					.iter()
+					.map(|mode| VideoMode {
+						width: mode.width,
+						height: mode.height,
+						refresh_rate: Some(mode.refresh_rate),
+					})
+					.collect(),
+				None => Vec::new(),
+			})
+	}
+
+	/// Switches between windowed, borderless and exclusive fullscreen
+	/// on the monitor in `settings.monitor`. The size change arrives as
+	/// a resize event. Stays windowed if no monitor is connected.
+	pub fn set_mode(&mut self, mode: WindowMode) {
+		if self.settings.mode == WindowMode::Windowed {
+			let (x, y) = self.internal_window.get_pos();
+			let (width, height) = self.internal_window.get_size();
+			self.windowed_rect = (x, y, width.max(1) as u32, height.max(1) as u32);
+		}
+
+		let window = &mut self.internal_window;
+		let index = self.settings.monitor;
+		let video_mode = self.settings.video_mode;
+		let (x, y, width, height) = self.windowed_rect;
+
+		let applied = self.glfw.with_connected_monitors(|_, monitors| {
+			let monitor = match (mode, select_monitor(monitors, index)) {
+				(WindowMode::Windowed, _) => {
+					window.set_monitor(glfw::WindowMode::Windowed, x, y, width, height, None);
+					return true;
+				}
+				(_, Some(monitor)) => monitor,
+				(_, None) => return false,
+			};
+
+			let (width, height, refresh_rate) = match mode {
+				// Matching the current video mode keeps the monitor from switching modes
+				WindowMode::Borderless => match monitor.get_video_mode() {
+					Some(current) => (current.width, current.height, Some(current.refresh_rate)),
+					None => return false,
+				},
+				_ => match video_mode {
+					Some(video_mode) => {
+						(video_mode.width, video_mode.height, video_mode.refresh_rate)
+					}
+					None => (width, height, None),
+				},
+			};
+			window.set_monitor(
+				glfw::WindowMode::FullScreen(monitor),
+				0,
+				0,
+				width,
+				height,
+				refresh_rate,
+			);
+			true
+		});
+
+		if applied {
+			self.settings.mode = mode;
+		}
+	}
+
+	pub fn set_vsync(&mut self, vsync: VSync) {
+		self.settings.vsync = vsync;
+		self.glfw.set_swap_interval(vsync.swap_interval());
+	}
+
+	pub fn set_resizable(&mut self, resizable: bool) {
+		self.settings.resizable = resizable;
+		self.internal_window.set_resizable(resizable);
+	}
+
+	pub fn set_title(&mut self, title: &str) {
+		self.settings.title = title.to_owned();
+		self.internal_window.set_title(title);
+	}
+
+	/// Loads the image at `path` as window icon, ignored on macOS.
+	pub fn set_icon(&mut self, path: &str) -> Result<(), image::ImageError> {
+		let image = image::open(path)?.to_rgba8();
+		let pixels = image
+			.pixels()
+			.map(|pixel| u32::from_ne_bytes(pixel.0))
+			.collect();
+
+		self.internal_window
+			.set_icon_from_pixels(vec![glfw::PixelImage {
+				width: image.width(),
+				height: image.height(),
+				pixels,
+			}]);
+		self.settings.icon = Some(path.to_owned());
+
+		Ok(())
+	}
+
 	// ----------- GLFW functions -----------
 }
 
@@ -127,6 +444,9 @@ impl Window {
 	pub fn default_setup(self) -> Window {
 		let wind = self.init();
 		wind.gl_enable(gl::DEPTH_TEST);
+		if wind.settings.samples > 0 {
+			wind.gl_enable(gl::MULTISAMPLE);
+		}
 
 		wind
 	}
@@ -145,3 +465,10 @@ impl Window {
 		}
 	}
 }
+
+/// Monitor at `index`, the primary one when None or out of range.
+fn select_monitor(monitors: &[glfw::Monitor], index: Option<usize>) -> Option<&glfw::Monitor> {
+	index
+		.and_then(|index| monitors.get(index))
+		.or_else(|| monitors.first())
+}